@@ -0,0 +1,236 @@
+//! Reading pages from the ASTs the Snooty parser produces, instead of
+//! from the RST source.
+//!
+//! ASTs are read from a directory laid out like `source/`, with one
+//! `.json` or `.bson` file per page (`source/foo/bar.txt` is
+//! `foo/bar.json`). Each file is either the page's root node or a page
+//! document with the root node in its `ast` field.
+//!
+//! The AST is turned back into just enough RST (directives, their options,
+//! code blocks and comments, each on the line the AST says it came from)
+//! for the same detectors to run on it, so the two backends can be compared.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::cache::Cache;
+use crate::files::is_page;
+use crate::types::Reason;
+use crate::vfs::{is_file, Vfs};
+
+const EXTENSIONS: &[&str] = &["json", "bson"];
+
+/// The AST file for the page at `path`, if there is one.
+pub fn ast_path(vfs: &dyn Vfs, ast_dir: &str, path: &str) -> Option<String> {
+    let rel = path.split("/source/").nth(1)?;
+    EXTENSIONS
+        .iter()
+        .map(|ext| {
+            String::from(
+                Path::new(ast_dir)
+                    .join(rel)
+                    .with_extension(ext)
+                    .to_string_lossy(),
+            )
+        })
+        .find(|p| is_file(vfs, p))
+}
+
+/// Parse an AST file's bytes, which may be JSON or BSON.
+pub fn parse(bytes: &[u8]) -> Value {
+    let value = match serde_json::from_slice::<Value>(bytes) {
+        Ok(value) => value,
+        Err(_) => match bson::Document::from_reader(bytes) {
+            Ok(document) => bson::Bson::Document(document).into_relaxed_extjson(),
+            Err(e) => {
+                eprintln!("Unable to parse AST: {e}");
+                Value::Null
+            }
+        },
+    };
+    match value.get("ast") {
+        Some(ast) => ast.clone(),
+        None => value,
+    }
+}
+
+/// The RST the detectors should see for the AST `root`.
+pub fn to_rst(root: &Value) -> String {
+    let mut lines: Vec<String> = vec![];
+    render(root, 0, &mut lines);
+    lines.join("\n") + "\n"
+}
+
+fn render(node: &Value, indent: usize, lines: &mut Vec<String>) {
+    let pad = " ".repeat(indent);
+    match node["type"].as_str().unwrap_or_default() {
+        "directive" => {
+            start_at(node, lines);
+            let name = directive_name(node);
+            let argument = text(&node["argument"]);
+            lines.push(
+                format!("{pad}.. {name}:: {argument}")
+                    .trim_end()
+                    .to_string(),
+            );
+            if let Some(options) = node["options"].as_object() {
+                for (key, value) in options {
+                    let value = match value {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    lines.push(format!("{pad}   :{key}: {value}").trim_end().to_string());
+                }
+            }
+            lines.push(String::new());
+            // Included content is inlined in the AST, but it's scanned
+            // from its own file, like for the text backend.
+            if name != "include" && name != "sharedinclude" {
+                render_children(node, indent + 3, lines);
+            }
+        }
+        "code" => {
+            start_at(node, lines);
+            let lang = node["lang"].as_str().unwrap_or_default();
+            lines.push(
+                format!("{pad}.. code-block:: {lang}")
+                    .trim_end()
+                    .to_string(),
+            );
+            lines.push(String::new());
+            for line in node["value"].as_str().unwrap_or_default().lines() {
+                lines.push(format!("{pad}   {line}"));
+            }
+            lines.push(String::new());
+        }
+        "comment" => {
+            start_at(node, lines);
+            lines.push(format!("{pad}.. {}", text(&node["children"])));
+            lines.push(String::new());
+        }
+        _ => render_children(node, indent, lines),
+    }
+}
+
+fn render_children(node: &Value, indent: usize, lines: &mut Vec<String>) {
+    for child in node["children"].as_array().into_iter().flatten() {
+        render(child, indent, lines);
+    }
+}
+
+// Pad with blank lines so the node lands on its (0-based) source line.
+fn start_at(node: &Value, lines: &mut Vec<String>) {
+    if let Some(line) = node["position"]["start"]["line"].as_u64() {
+        while lines.len() < line as usize {
+            lines.push(String::new());
+        }
+    }
+}
+
+fn directive_name(node: &Value) -> String {
+    let name = node["name"].as_str().unwrap_or_default();
+    // Snooty turns `tabs-drivers` into `tabs` with `:tabset: drivers`.
+    // Put it back, since that's what the code tabs detector looks for.
+    if name == "tabs" && node["options"]["tabset"].as_str() == Some("drivers") {
+        return String::from("tabs-drivers");
+    }
+    name.to_string()
+}
+
+// The text of `nodes`, which may be a list of nodes or a single node.
+fn text(nodes: &Value) -> String {
+    match nodes {
+        Value::Array(nodes) => nodes.iter().map(text).collect(),
+        Value::Object(_) => match nodes["value"].as_str() {
+            Some(value) => value.to_string(),
+            None => text(&nodes["children"]),
+        },
+        _ => String::new(),
+    }
+}
+
+/// Compare the Reasons found from the source (`text`) with those found
+/// from ASTs (`ast`), for every page that has an AST.
+/// Returns how many differences there were.
+pub fn cross_check(text: &Cache, ast: &Cache, ast_dir: &str) -> usize {
+    let text_strings = text.code_example_strings();
+    let ast_strings = ast.code_example_strings();
+    let mut count = 0;
+    for (path, _) in ast.entries() {
+        if !is_page(path) || ast_path(ast.vfs(), ast_dir, path).is_none() {
+            continue;
+        }
+        let from_text: BTreeSet<Reason> = text.page_reasons(path, &text_strings);
+        let from_ast: BTreeSet<Reason> = ast.page_reasons(path, &ast_strings);
+        for reason in from_text.difference(&from_ast) {
+            println!("{path}: only in source: {}: {reason}", reason.id());
+            count += 1;
+        }
+        for reason in from_ast.difference(&from_text) {
+            println!("{path}: only in AST: {}: {reason}", reason.id());
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_json_and_bson_page_documents() {
+        let root = json!({"type": "root", "children": []});
+        let page = json!({"filename": "index.txt", "ast": root});
+        assert_eq!(parse(page.to_string().as_bytes()), root);
+        assert_eq!(parse(root.to_string().as_bytes()), root);
+
+        let document = bson::doc! {"ast": {"type": "root", "children": []}};
+        let mut bytes: Vec<u8> = vec![];
+        document.to_writer(&mut bytes).unwrap();
+        assert_eq!(parse(&bytes), root);
+    }
+
+    #[test]
+    fn renders_directives_on_their_source_lines() {
+        let root = json!({"type": "root", "children": [
+            {"type": "comment", "position": {"start": {"line": 0}},
+             "children": [{"type": "text", "value": "codetagger: ignore go"}]},
+            {"type": "directive", "name": "tabs", "position": {"start": {"line": 2}},
+             "argument": [], "options": {"tabset": "drivers"}, "children": [
+                {"type": "directive", "name": "tab", "position": {"start": {"line": 5}},
+                 "argument": [], "options": {"tabid": "python"}, "children": [
+                    {"type": "code", "lang": "python", "position": {"start": {"line": 8}},
+                     "value": "print(1)"}
+                ]}
+            ]},
+            {"type": "directive", "name": "include", "position": {"start": {"line": 12}},
+             "argument": [{"type": "text", "value": "/includes/a.rst"}], "children": [
+                {"type": "code", "lang": "go", "value": "fmt.Println(1)"}
+            ]}
+        ]});
+        assert_eq!(
+            to_rst(&root),
+            "\
+.. codetagger: ignore go
+
+.. tabs-drivers::
+   :tabset: drivers
+
+   .. tab::
+      :tabid: python
+
+      .. code-block:: python
+
+         print(1)
+
+.. include:: /includes/a.rst
+
+"
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Running over several docs repos, as listed in a manifest.
+//!
+//! The manifest is a TOML file with a `[[repos]]` table per repo. Besides
+//! `path` (relative to the manifest), each can have any of the settings
+//! from `.codetagger/config.toml`, which override the repo's own:
+//!
+//! ```toml
+//! [[repos]]
+//! path = "../docs-java"
+//!
+//! [[repos]]
+//! path = "../docs-rust"
+//! detectors = ["code-example", "languages"]
+//! lang-sources = ["tabs"]
+//! ```
+
+use std::fs::read_to_string;
+use std::io;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, ConfigOverrides};
+use crate::diagnostics::{diagnose, DiagnosticKind};
+use crate::repo::Repo;
+use crate::suppressions::unused_suppressions;
+use crate::vfs::DiskFs;
+
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    pub repos: Vec<ManifestRepo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ManifestRepo {
+    pub path: String,
+    #[serde(flatten)]
+    pub overrides: ConfigOverrides,
+}
+
+impl Manifest {
+    /// Read the manifest at `path`, making repo paths relative to it.
+    pub fn load(path: &str) -> Manifest {
+        let contents =
+            read_to_string(path).unwrap_or_else(|_| panic!("Unable to read manifest {path}"));
+        let mut manifest: Manifest =
+            toml::from_str(&contents).unwrap_or_else(|e| panic!("Bad manifest {path}: {e}"));
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        for repo in &mut manifest.repos {
+            repo.path = String::from(dir.join(&repo.path).to_string_lossy());
+            if let Some(shared_dir) = &mut repo.overrides.shared_dir {
+                *shared_dir =
+                    String::from(Path::new(&repo.path).join(&shared_dir).to_string_lossy());
+            }
+        }
+        manifest
+    }
+}
+
+/// What happened in one repo.
+#[derive(Debug, Default, Serialize)]
+pub struct RepoSummary {
+    pub repo: String,
+    pub pages: usize,
+    pub pages_needing_edits: usize,
+    pub missing_keywords: usize,
+    pub missing_languages: usize,
+    pub stale_languages: usize,
+    pub unknown_tabids: usize,
+    pub unused_suppressions: usize,
+    /// Set if the run panicked, in which case the counts are meaningless.
+    pub error: Option<String>,
+}
+
+/// Check (and with `dryrun` off, tag) every repo in `manifest`.
+/// A repo that can't be read, or panics, is recorded in its summary
+/// and doesn't stop the others.
+pub fn run_batch(manifest: &Manifest, dryrun: bool, no_cache: bool) -> Vec<RepoSummary> {
+    let mut summaries: Vec<RepoSummary> = vec![];
+    for repo in &manifest.repos {
+        println!("\n📚 {}", repo.path);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            run_repo(&repo.path, &repo.overrides, dryrun, no_cache)
+        }));
+        let message = match result {
+            Ok(Ok(summary)) => {
+                summaries.push(summary);
+                continue;
+            }
+            Ok(Err(e)) => format!("Unable to check {}: {e}", repo.path),
+            Err(payload) => payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| String::from("unknown error")),
+        };
+        summaries.push(RepoSummary {
+            repo: repo.path.clone(),
+            error: Some(message),
+            ..Default::default()
+        });
+    }
+    summaries
+}
+
+fn run_repo(
+    repo: &str,
+    overrides: &ConfigOverrides,
+    dryrun: bool,
+    no_cache: bool,
+) -> io::Result<RepoSummary> {
+    let mut config = Config::for_repo(&DiskFs, repo);
+    overrides.apply(&mut config);
+    if overrides.current_version.is_some() {
+        config.load_project(&DiskFs, repo);
+    }
+    // Versioned repos only get their current version checked.
+    let repo = Repo::with_config(repo, config)?;
+    if repo.root != repo.path {
+        println!("📚 Checking {}", repo.root);
+    }
+    let mut cache = repo.cache(no_cache);
+    let scan = repo.scan(&mut cache)?;
+
+    let mut summary = RepoSummary {
+        repo: repo.root.clone(),
+        unknown_tabids: cache.unknown_tabids().len(),
+        ..Default::default()
+    };
+    // Count what's wrong before the edits fix it.
+    for (path, expected) in &scan.expected {
+        summary.pages += 1;
+        let contents = repo.vfs().read(path).unwrap_or_default();
+        let diagnostics = diagnose(path, &contents, expected);
+        if !diagnostics.is_empty() {
+            summary.pages_needing_edits += 1;
+        }
+        for diagnostic in &diagnostics {
+            match &diagnostic.kind {
+                DiagnosticKind::MissingKeyword(_) => summary.missing_keywords += 1,
+                DiagnosticKind::MissingLanguages(_) => summary.missing_languages += 1,
+                DiagnosticKind::StaleLanguages(_) => summary.stale_languages += 1,
+            }
+        }
+    }
+
+    scan.apply(repo.vfs(), dryrun);
+
+    let unused = unused_suppressions(&cache, &repo.suppressions());
+    if !unused.is_empty() {
+        println!("\n🤷 Unused suppressions:");
+        for line in &unused {
+            println!("  {line}");
+        }
+    }
+    summary.unused_suppressions = unused.len();
+
+    cache.save();
+    repo.check_single_pl_facet();
+    Ok(summary)
+}
+
+pub fn print_summaries(summaries: &[RepoSummary]) {
+    println!(
+        "\n{:<40} {:>6} {:>10} {:>9} {:>9} {:>6} {:>8} {:>7}",
+        "Repo", "Pages", "Need edits", "Keywords", "Missing", "Stale", "Tabids", "Unused"
+    );
+    for s in summaries.iter().filter(|s| s.error.is_none()) {
+        println!(
+            "{:<40} {:>6} {:>10} {:>9} {:>9} {:>6} {:>8} {:>7}",
+            s.repo,
+            s.pages,
+            s.pages_needing_edits,
+            s.missing_keywords,
+            s.missing_languages,
+            s.stale_languages,
+            s.unknown_tabids,
+            s.unused_suppressions
+        );
+    }
+    for s in summaries {
+        if let Some(error) = &s.error {
+            println!("❌ {}: {error}", s.repo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_repo_is_an_error() {
+        let manifest = Manifest {
+            repos: vec![ManifestRepo {
+                path: String::from("/nonexistent/codetagger/docs"),
+                overrides: ConfigOverrides::default(),
+            }],
+        };
+        let summaries = run_batch(&manifest, true, true);
+        assert_eq!(summaries.len(), 1);
+        assert!(summaries[0]
+            .error
+            .as_ref()
+            .is_some_and(|e| e.contains("Unable to check")));
+    }
+}
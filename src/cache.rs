@@ -0,0 +1,724 @@
+//! An on-disk cache of per-file scan results, keyed by content hash.
+//!
+//! Each entry only depends on the contents of its own file. Anything that
+//! depends on *other* files (such as whether a page includes a file with code
+//! tabs) is worked out from the cached entries on every run, so editing an
+//! include never leaves a stale answer behind for the pages that include it.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::read_to_string;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ast;
+use crate::config::Config;
+use crate::diagnostics::ExpectedTags;
+use crate::meta::*;
+use crate::rst;
+use crate::snooty::{resolve_constants, resolve_sharedinclude};
+use crate::suppressions::{
+    filter_ignored_tags, ignores_reason, parse_ignore_directives, IgnoreDirective,
+};
+use crate::types::{CodeExampleKind, Language, Reason, Variant};
+use crate::vfs::{walk, DiskFs, Vfs};
+use crate::yaml;
+use crate::{CODE_TABS_STRINGS_1, CODE_TABS_STRINGS_2};
+
+/// Bump this whenever the detectors change, so old caches are thrown away.
+const CACHE_VERSION: u32 = 19;
+
+/// Directory inside the docs repo for codetagger's own files.
+pub const CACHE_DIR: &str = ".codetagger";
+
+/// Everything we learn from a single file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub hash: String,
+    /// Lines containing an include-like directive
+    /// (`include::`, `literalinclude::`, `sharedinclude::`).
+    pub includes: Vec<String>,
+    pub keywords: Option<Vec<String>>,
+    pub facets: Option<BTreeSet<Language>>,
+    /// Values of every facet on the page, by facet name.
+    pub facet_values: BTreeMap<String, BTreeSet<String>>,
+    /// The code tabs string found on the page, if any.
+    pub code_tabs: Option<String>,
+    /// Results of the detectors that only look at this file.
+    pub reasons: BTreeSet<Reason>,
+    /// `.. codetagger: ignore` comments in the file.
+    pub ignores: Vec<IgnoreDirective>,
+    /// Driver tabids that aren't a known Language, and their 0-based lines.
+    pub unknown_tabids: Vec<(usize, String)>,
+    /// For YAML files, the include paths (relative to `source/`)
+    /// pages use for what they render as.
+    pub generated_names: Vec<String>,
+}
+
+impl CacheEntry {
+    pub fn from_contents(contents: &str, config: &Config) -> CacheEntry {
+        let hash = content_hash(contents);
+        // The detectors should see the real values of constants,
+        // but the hash is of what's on disk.
+        let contents = &resolve_constants(contents, &config.constants);
+        let lines: Vec<String> = contents.lines().map(String::from).collect();
+
+        let includes = lines
+            .iter()
+            .filter(|line| line.contains("include::"))
+            .map(|line| match &config.shared_dir {
+                Some(dir) => resolve_sharedinclude(line.trim(), dir),
+                None => line.trim().to_string(),
+            })
+            .collect();
+        let directives = rst::parse(&lines);
+        let code_tabs = lines.iter().find_map(|line| {
+            [CODE_TABS_STRINGS_1, CODE_TABS_STRINGS_2]
+                .into_iter()
+                .find(|s| line.contains(s))
+                .map(String::from)
+        });
+
+        CacheEntry {
+            hash,
+            includes,
+            keywords: meta_keywords_from_lines(&lines),
+            facets: pl_facet_values_from_str(contents),
+            facet_values: get_facets(&directives),
+            code_tabs,
+            reasons: content_reasons(&lines, config)
+                .into_iter()
+                .filter(|r| config.detects(r))
+                .collect(),
+            ignores: parse_ignore_directives(&lines),
+            unknown_tabids: get_driver_tabids(&directives)
+                .into_iter()
+                .filter(|(_, tabid)| {
+                    Language::from_str(tabid).is_err()
+                        && Variant::from_tabid(tabid).is_none()
+                        && tab_reason(tabid).is_none()
+                })
+                .collect(),
+            generated_names: vec![],
+        }
+    }
+
+    /// The file's own Reasons, minus any it ignores.
+    pub fn active_reasons(&self) -> BTreeSet<Reason> {
+        self.reasons
+            .iter()
+            .filter(|r| !ignores_reason(&self.ignores, r))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Things that make a page need a code example keyword, and which kind.
+pub type CodeExampleStrings = BTreeMap<String, CodeExampleKind>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    version: u32,
+    /// Entries are only valid for the config they were made with.
+    config: Config,
+    entries: BTreeMap<String, CacheEntry>,
+    /// Where to save the cache. `None` means don't persist it.
+    #[serde(skip)]
+    location: Option<PathBuf>,
+    /// Files looked at during this run; everything else is pruned on save.
+    #[serde(skip)]
+    seen: BTreeSet<String>,
+    /// Where files are read from. `None` means the real filesystem.
+    #[serde(skip)]
+    vfs: Option<Arc<dyn Vfs>>,
+    /// Who includes whom, built from the entries when first needed
+    /// and thrown away whenever an entry changes.
+    #[serde(skip)]
+    index: OnceLock<IncludeIndex>,
+}
+
+#[derive(Debug, Default)]
+struct IncludeIndex {
+    /// The files each file includes directly.
+    includes: HashMap<String, BTreeSet<String>>,
+    /// The files that directly include each file.
+    includers: HashMap<String, BTreeSet<String>>,
+}
+
+impl IncludeIndex {
+    fn build(cache: &Cache) -> IncludeIndex {
+        let sources =
+            || (cache.all_entries()).filter(|(p, _)| p.contains("/source/") || cache.is_shared(p));
+        let mut by_name: HashMap<String, Vec<&String>> = HashMap::default();
+        for (path, entry) in sources() {
+            for name in cache.include_names(path, entry) {
+                by_name.entry(name).or_default().push(path);
+            }
+        }
+
+        let mut index = IncludeIndex::default();
+        for (path, entry) in sources() {
+            for target in entry
+                .includes
+                .iter()
+                .filter_map(|l| include_target(path, l))
+            {
+                for included in by_name.get(&target).into_iter().flatten() {
+                    index
+                        .includes
+                        .entry(path.clone())
+                        .or_default()
+                        .insert(included.to_string());
+                    index
+                        .includers
+                        .entry(included.to_string())
+                        .or_default()
+                        .insert(path.clone());
+                }
+            }
+        }
+        index
+    }
+}
+
+impl Cache {
+    /// A cache that lives only as long as this run.
+    pub fn in_memory(config: &Config) -> Cache {
+        Cache {
+            version: CACHE_VERSION,
+            config: config.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Read (and edit) files through `vfs` instead of the real filesystem.
+    pub fn with_vfs(mut self, vfs: Arc<dyn Vfs>) -> Cache {
+        self.vfs = Some(vfs);
+        self
+    }
+
+    pub fn vfs(&self) -> &dyn Vfs {
+        self.vfs.as_deref().unwrap_or(&DiskFs)
+    }
+
+    /// Load the cache for `repo`, or start an empty one.
+    pub fn load(repo: &str, config: &Config) -> Cache {
+        let location = cache_location(repo);
+        let mut cache = read_to_string(&location)
+            .ok()
+            .and_then(|s| serde_json::from_str::<Cache>(&s).ok())
+            .filter(|c| c.version == CACHE_VERSION && c.config == *config)
+            .unwrap_or_else(|| Cache::in_memory(config));
+        cache.location = Some(location);
+        cache
+    }
+
+    pub fn save(&mut self) {
+        let Some(location) = &self.location else {
+            return;
+        };
+        let seen = &self.seen;
+        self.entries.retain(|path, _| seen.contains(path));
+
+        if let Some(dir) = location.parent() {
+            std::fs::create_dir_all(dir).expect("Unable to create cache dir");
+        }
+        let json = serde_json::to_string(&self).expect("Unable to serialize cache");
+        std::fs::write(location, json).expect("Unable to write cache");
+    }
+
+    /// Get the entry for `path`, re-scanning the file if its contents changed.
+    pub fn get(&mut self, path: &str) -> &CacheEntry {
+        let ast_path =
+            (self.config.ast_dir.as_deref()).and_then(|dir| ast::ast_path(self.vfs(), dir, path));
+        if let Some(ast_path) = ast_path {
+            let bytes = self.vfs().read_bytes(&ast_path).unwrap_or_default();
+            return self.set_ast(path, &bytes);
+        }
+        let contents = self.vfs().read(path).unwrap_or_default();
+        self.set_contents(path, &contents)
+    }
+
+    /// Like `set_contents`, but from the page's Snooty AST.
+    fn set_ast(&mut self, path: &str, bytes: &[u8]) -> &CacheEntry {
+        let new = self.seen.insert(path.to_string());
+        let hash = blake3::hash(bytes).to_hex().to_string();
+        let fresh = self.entries.get(path).is_some_and(|e| e.hash == hash);
+        if new || !fresh {
+            self.index.take();
+        }
+        if !fresh {
+            let rst = ast::to_rst(&ast::parse(bytes));
+            let mut entry = CacheEntry::from_contents(&rst, &self.config);
+            entry.hash = hash;
+            self.entries.insert(path.to_string(), entry);
+        }
+        &self.entries[path]
+    }
+
+    /// Like `get`, but for contents that may not have been saved to disk yet.
+    pub fn set_contents(&mut self, path: &str, contents: &str) -> &CacheEntry {
+        let new = self.seen.insert(path.to_string());
+        let hash = content_hash(contents);
+        let fresh = self.entries.get(path).is_some_and(|e| e.hash == hash);
+        if new || !fresh {
+            self.index.take();
+        }
+        if !fresh {
+            let entry = if yaml::renders_to_rst(path) {
+                let mut entry = CacheEntry::from_contents(&yaml::to_rst(contents), &self.config);
+                entry.hash = hash;
+                entry.generated_names = yaml::generated_names(path, contents);
+                entry
+            } else {
+                CacheEntry::from_contents(contents, &self.config)
+            };
+            self.entries.insert(path.to_string(), entry);
+        }
+        &self.entries[path]
+    }
+
+    /// Scan (or look up) every file in `repo`, returning their paths.
+    /// Fails if `repo` (or the shared dir) can't be read.
+    pub fn scan(&mut self, repo: &str) -> io::Result<Vec<String>> {
+        let filepaths = walk(self.vfs(), repo, &[".git", CACHE_DIR])?;
+        for filepath in &filepaths {
+            self.get(filepath);
+        }
+        // Pages can include anything in the shared repo.
+        if let Some(dir) = self.config.shared_dir.clone() {
+            for filepath in walk(self.vfs(), &dir, &[".git"])? {
+                self.get(&filepath);
+            }
+        }
+        Ok(filepaths)
+    }
+
+    /// Drop a file that no longer exists.
+    pub fn remove(&mut self, path: &str) {
+        self.seen.remove(path);
+        self.entries.remove(path);
+        self.index.take();
+    }
+
+    pub fn entry(&self, path: &str) -> Option<&CacheEntry> {
+        self.entries.get(path)
+    }
+
+    /// The docs repo's files. Files from the shared repo are left out,
+    /// since they're only there for pages to include.
+    pub fn entries(&self) -> impl Iterator<Item = (&String, &CacheEntry)> {
+        self.all_entries().filter(|(path, _)| !self.is_shared(path))
+    }
+
+    fn all_entries(&self) -> impl Iterator<Item = (&String, &CacheEntry)> {
+        self.entries
+            .iter()
+            .filter(|(path, _)| self.seen.contains(*path))
+    }
+
+    fn is_shared(&self, path: &str) -> bool {
+        (self.config.shared_dir.as_ref()).is_some_and(|dir| Path::new(path).starts_with(dir))
+    }
+
+    // The paths pages use to include `path`: its own (the full path for
+    // shared includes), plus what it's rendered as if it's YAML.
+    fn include_names(&self, path: &str, entry: &CacheEntry) -> Vec<String> {
+        if self.is_shared(path) {
+            return vec![path.to_string()];
+        }
+        let mut names = vec![rel_path(path)];
+        names.extend(entry.generated_names.iter().cloned());
+        names
+    }
+
+    /// The includes with code tabs, or directives that count as code
+    /// examples, by the names pages include them by.
+    pub fn includes_with_code_examples(&self) -> Vec<(String, CodeExampleKind)> {
+        self.all_entries()
+            .filter(|(path, _)| path.contains("/source/includes/") || self.is_shared(path))
+            .filter(|(_, entry)| {
+                !ignores_reason(
+                    &entry.ignores,
+                    &Reason::CodeExample(CodeExampleKind::Snippet, String::new()),
+                )
+            })
+            .filter_map(|(path, entry)| {
+                let kind = match entry.code_tabs {
+                    Some(_) => CodeExampleKind::Tabbed,
+                    None => entry
+                        .reasons
+                        .iter()
+                        .filter_map(|r| match r {
+                            Reason::CodeExample(kind, _) => Some(*kind),
+                            _ => None,
+                        })
+                        .max()?,
+                };
+                Some((path, entry, kind))
+            })
+            .flat_map(|(path, entry, kind)| {
+                self.include_names(path, entry)
+                    .into_iter()
+                    .map(move |name| (name, kind))
+            })
+            .collect()
+    }
+
+    fn index(&self) -> &IncludeIndex {
+        self.index.get_or_init(|| IncludeIndex::build(self))
+    }
+
+    /// The files that directly include `path`.
+    pub fn files_that_include_this_file(&self, path: &str) -> BTreeSet<String> {
+        self.index()
+            .includers
+            .get(path)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Every file that includes `path`, directly or through other includes.
+    pub fn transitive_includers(&self, path: &str) -> BTreeSet<String> {
+        follow(&self.index().includers, path)
+    }
+
+    /// Every file included by `path`, directly or through other includes.
+    pub fn transitive_includes(&self, path: &str) -> BTreeSet<String> {
+        follow(&self.index().includes, path)
+    }
+
+    /// Whether the page has code tabs or includes a file with code
+    /// examples, directly or through other includes. `strings` is the
+    /// includes with code examples, by name, and their kinds.
+    fn code_example_reason(
+        &self,
+        path: &str,
+        entry: &CacheEntry,
+        strings: &CodeExampleStrings,
+    ) -> Option<Reason> {
+        if path.contains("/includes/") {
+            return None;
+        }
+        if let Some(s) = &entry.code_tabs {
+            return Some(Reason::CodeExample(CodeExampleKind::Tabbed, s.clone()));
+        }
+        let included = self.transitive_includes(path);
+        let files = included
+            .iter()
+            .filter_map(|p| Some((p.as_str(), self.entries.get(p)?)));
+        std::iter::once((path, entry))
+            .chain(files)
+            .flat_map(|(file, e)| e.includes.iter().filter_map(|l| include_target(file, l)))
+            .filter_map(|target| Some(Reason::CodeExample(*strings.get(&target)?, target)))
+            .max()
+    }
+
+    /// The reasons `path` itself needs tagging, minus any it ignores.
+    pub fn own_reasons(&self, path: &str, strings: &CodeExampleStrings) -> BTreeSet<Reason> {
+        let Some(entry) = self.entries.get(path) else {
+            return BTreeSet::default();
+        };
+        let mut reasons = entry.reasons.clone();
+        reasons.extend(
+            self.code_example_reason(path, entry, strings)
+                .filter(|r| self.config.detects(r)),
+        );
+        reasons.retain(|r| !ignores_reason(&entry.ignores, r));
+        reasons
+    }
+
+    /// All the reasons `path` needs tagging: its own, plus the
+    /// languages of everything it includes.
+    pub fn page_reasons(&self, path: &str, strings: &CodeExampleStrings) -> BTreeSet<Reason> {
+        self.collect_page_reasons(path, strings, true)
+    }
+
+    /// Like `page_reasons`, but ignoring `.. codetagger: ignore` comments.
+    pub fn unfiltered_page_reasons(
+        &self,
+        path: &str,
+        strings: &CodeExampleStrings,
+    ) -> BTreeSet<Reason> {
+        self.collect_page_reasons(path, strings, false)
+    }
+
+    fn collect_page_reasons(
+        &self,
+        path: &str,
+        strings: &CodeExampleStrings,
+        filtered: bool,
+    ) -> BTreeSet<Reason> {
+        let Some(entry) = self.entries.get(path) else {
+            return BTreeSet::default();
+        };
+        let mut reasons = entry.reasons.clone();
+        reasons.extend(
+            self.code_example_reason(path, entry, strings)
+                .filter(|r| self.config.detects(r)),
+        );
+        if filtered {
+            reasons.retain(|r| !ignores_reason(&entry.ignores, r));
+        }
+
+        let mut langs: BTreeSet<Language> = BTreeSet::default();
+        for included in self.transitive_includes(path) {
+            let included_entry = &self.entries[&included];
+            let included_reasons = if filtered {
+                included_entry.active_reasons()
+            } else {
+                included_entry.reasons.clone()
+            };
+            for reason in included_reasons {
+                if let Reason::Languages(l) = reason {
+                    langs.extend(l);
+                }
+            }
+        }
+
+        // One Languages reason for the page and everything it includes.
+        if !langs.is_empty() {
+            reasons.retain(|r| match r {
+                Reason::Languages(l) => {
+                    langs.extend(l.iter().cloned());
+                    false
+                }
+                _ => true,
+            });
+            reasons.insert(Reason::Languages(langs));
+        }
+        reasons
+    }
+
+    /// The tags `path` should have, minus any it ignores.
+    pub fn expected_tags(&self, path: &str, strings: &CodeExampleStrings) -> ExpectedTags {
+        let mut expected = ExpectedTags::from_reasons(&self.page_reasons(path, strings));
+        if let Some(entry) = self.entries.get(path) {
+            filter_ignored_tags(&entry.ignores, &mut expected);
+        }
+        expected
+    }
+
+    /// Every unknown driver tabid, with the files and 0-based lines it's on,
+    /// most common first.
+    pub fn unknown_tabids(&self) -> Vec<(String, Vec<(String, usize)>)> {
+        let mut found: BTreeMap<String, Vec<(String, usize)>> = BTreeMap::default();
+        for (path, entry) in self.entries() {
+            for (line, tabid) in &entry.unknown_tabids {
+                found
+                    .entry(tabid.clone())
+                    .or_default()
+                    .push((path.clone(), *line));
+            }
+        }
+        let mut found: Vec<_> = found.into_iter().collect();
+        found.sort_by_key(|(_, locations)| std::cmp::Reverse(locations.len()));
+        found
+    }
+
+    /// The strings that make a page need a "code example" tag.
+    pub fn code_example_strings(&self) -> CodeExampleStrings {
+        let mut strings = CodeExampleStrings::default();
+        for (name, kind) in self.includes_with_code_examples() {
+            let best = strings.entry(name).or_insert(kind);
+            *best = kind.max(*best);
+        }
+        strings.insert(CODE_TABS_STRINGS_1.to_string(), CodeExampleKind::Tabbed);
+        strings.insert(CODE_TABS_STRINGS_2.to_string(), CodeExampleKind::Tabbed);
+        strings
+    }
+}
+
+fn content_hash(contents: &str) -> String {
+    blake3::hash(contents.as_bytes()).to_hex().to_string()
+}
+
+fn rel_path(path: &str) -> String {
+    path.split("/source/").collect::<Vec<_>>()[1].to_string()
+}
+
+// Everything reachable from `path` in `edges`.
+fn follow(edges: &HashMap<String, BTreeSet<String>>, path: &str) -> BTreeSet<String> {
+    let mut found: BTreeSet<String> = BTreeSet::default();
+    let mut queue = vec![path.to_string()];
+    while let Some(file) = queue.pop() {
+        for next in edges.get(&file).into_iter().flatten() {
+            if found.insert(next.clone()) {
+                queue.push(next.clone());
+            }
+        }
+    }
+    found
+}
+
+// The path (relative to `source/`) that the include-like `line` in `path`
+// points at. Targets starting with `/` are relative to `source/`,
+// others to the including file. Shared includes were already resolved
+// to their full path.
+fn include_target(path: &str, line: &str) -> Option<String> {
+    let (directive, target) = line.split_once("::")?;
+    let target = target.trim();
+    if target.is_empty() {
+        return None;
+    }
+    if directive.ends_with("sharedinclude") {
+        return Some(target.to_string());
+    }
+    let joined = match target.strip_prefix('/') {
+        Some(target) => PathBuf::from(target),
+        None => {
+            let rel = path.split("/source/").nth(1)?;
+            Path::new(rel).parent()?.join(target)
+        }
+    };
+    let mut parts: Vec<&str> = vec![];
+    for part in joined.to_str()?.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+// Prefer the user's cache dir so we don't leave files around in docs repos.
+fn cache_location(repo: &str) -> PathBuf {
+    let canonical = Path::new(repo)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(repo));
+    let key = blake3::hash(canonical.to_string_lossy().as_bytes()).to_hex();
+
+    match dirs::cache_dir() {
+        Some(dir) => dir.join("codetagger").join(format!("{}.json", &key[..16])),
+        None => Path::new(repo).join(CACHE_DIR).join("cache.json"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+
+    const PAGE: &str = "/docs/source/page.txt";
+    const A: &str = "/docs/source/includes/a.rst";
+    const B: &str = "/docs/source/includes/b.rst";
+
+    fn memory_cache(page: &str) -> (Arc<MemoryFs>, Cache) {
+        let vfs = Arc::new(MemoryFs::new(
+            [
+                (PAGE, page),
+                (A, ".. code-block:: go\n\n   fmt.Println(1)\n"),
+                (B, ".. code-block:: python\n\n   print(1)\n"),
+            ]
+            .map(|(path, contents)| (path.to_string(), contents.to_string())),
+        ));
+        let mut cache = Cache::in_memory(&Config::default()).with_vfs(vfs.clone());
+        cache.scan("/docs").unwrap();
+        (vfs, cache)
+    }
+
+    #[test]
+    fn known_tab_keywords_arent_unknown_tabids() {
+        let entry = CacheEntry::from_contents(
+            "\
+.. tabs-drivers::
+
+   .. tab::
+      :tabid: compass
+
+   .. tab::
+      :tabid: atlas-ui
+
+   .. tab::
+      :tabid: java-sync
+
+   .. tab::
+      :tabid: python
+
+   .. tab::
+      :tabid: cobol
+",
+            &Config::default(),
+        );
+        assert_eq!(entry.unknown_tabids, [(14, String::from("cobol"))]);
+    }
+
+    #[test]
+    fn unchanged_files_keep_their_entry() {
+        let (_, mut cache) = memory_cache(".. include:: /includes/a.rst\n");
+        let hash = cache.get(PAGE).hash.clone();
+        assert_eq!(cache.get(PAGE).hash, hash);
+        assert_eq!(cache.entry(PAGE).unwrap().includes.len(), 1);
+    }
+
+    #[test]
+    fn changed_files_are_rescanned() {
+        let (vfs, mut cache) = memory_cache(".. include:: /includes/a.rst\n");
+        let hash = cache.get(PAGE).hash.clone();
+        assert!(cache.entry(PAGE).unwrap().keywords.is_none());
+
+        vfs.write(
+            PAGE,
+            ".. meta::\n   :keywords: go\n\n.. include:: /includes/a.rst\n",
+        )
+        .unwrap();
+        let entry = cache.get(PAGE);
+        assert_ne!(entry.hash, hash);
+        assert_eq!(entry.keywords, Some(vec![String::from("go")]));
+    }
+
+    #[test]
+    fn include_index_follows_edits() {
+        let (vfs, mut cache) = memory_cache(".. include:: /includes/a.rst\n");
+        assert_eq!(
+            cache.transitive_includes(PAGE),
+            BTreeSet::from([A.to_string()])
+        );
+        assert_eq!(
+            cache.files_that_include_this_file(A),
+            BTreeSet::from([PAGE.to_string()])
+        );
+
+        vfs.write(PAGE, ".. include:: /includes/b.rst\n").unwrap();
+        cache.get(PAGE);
+        assert_eq!(
+            cache.transitive_includes(PAGE),
+            BTreeSet::from([B.to_string()])
+        );
+        assert!(cache.files_that_include_this_file(A).is_empty());
+        let strings = cache.code_example_strings();
+        assert_eq!(
+            cache.expected_tags(PAGE, &strings).languages,
+            BTreeSet::from([Language::Python])
+        );
+    }
+
+    #[test]
+    fn nested_includes_count_as_code_examples() {
+        const C: &str = "/docs/source/includes/c.rst";
+        let vfs = Arc::new(MemoryFs::new(
+            [
+                (PAGE, ".. include:: /includes/a.rst\n"),
+                (A, ".. include:: /includes/c.rst\n"),
+                (
+                    C,
+                    ".. io-code-block::\n\n   .. input::\n      :language: go\n",
+                ),
+            ]
+            .map(|(path, contents)| (path.to_string(), contents.to_string())),
+        ));
+        let mut cache = Cache::in_memory(&Config::default()).with_vfs(vfs);
+        cache.scan("/docs").unwrap();
+        let strings = cache.code_example_strings();
+        assert!(cache
+            .expected_tags(PAGE, &strings)
+            .keywords
+            .contains("code example"));
+    }
+}
@@ -1,4 +1,7 @@
-use clap::{ArgAction, Parser};
+use clap::{ArgAction, Parser, Subcommand};
+
+use crate::config::{Config, LangSource};
+use crate::vfs::Vfs;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -15,4 +18,145 @@ pub struct Args {
     /// Print information on matches.
     #[arg(short, long)]
     pub verbose: bool,
+    /// Where to look for programming languages (comma-separated).
+    /// Defaults to all of them.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub lang_sources: Option<Vec<LangSource>>,
+    /// Exit with an error if any driver tabs have a tabid
+    /// that isn't a known programming language.
+    #[arg(long)]
+    pub fail_on_unknown_tabids: bool,
+    /// Don't read or write the scan cache.
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Read pages from Snooty parser ASTs (`.json` or `.bson`)
+    /// in this directory instead of from their source.
+    #[arg(long)]
+    pub ast: Option<String>,
+    /// For repos with a directory per version, the version to check.
+    /// Defaults to `current`.
+    #[arg(long)]
+    pub docs_version: Option<String>,
+    /// Scan this git revision (a tag, branch or commit) instead of
+    /// the working tree. Nothing is edited.
+    #[arg(long)]
+    pub rev: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Arguments for `taxer`.
+#[derive(Parser, Debug)]
+#[command(version, about = "Find and fix near-duplicate keywords", long_about = None)]
+pub struct TaxerArgs {
+    /// In order to make changes to the files,
+    /// run `with --dryrun=false`.
+    #[clap(long, short,
+           default_missing_value("true"), default_value("true"), num_args(0..=1),
+           require_equals(true), action = ArgAction::Set)]
+    pub dryrun: bool,
+    /// Path to the root of the target repo.
+    #[arg(short, long)]
+    pub repo: String,
+    /// Print every keyword and page.
+    #[arg(short, long)]
+    pub verbose: bool,
+    /// How many edits apart two keywords can be and still look alike.
+    #[arg(long, default_value_t = 3)]
+    pub max_dist: usize,
+    /// Don't read or write the scan cache.
+    #[arg(long)]
+    pub no_cache: bool,
+    #[command(subcommand)]
+    pub command: Option<TaxerCommand>,
+}
+
+/// Arguments for `codetagger-batch`.
+#[derive(Parser, Debug)]
+#[command(version, about = "Run codetagger over every repo in a manifest", long_about = None)]
+pub struct BatchArgs {
+    /// In order to make changes to the files,
+    /// run `with --dryrun=false`.
+    #[clap(long, short,
+           default_missing_value("true"), default_value("true"), num_args(0..=1),
+           require_equals(true), action = ArgAction::Set)]
+    pub dryrun: bool,
+    /// Path to the manifest.
+    pub manifest: String,
+    /// Also write the combined report as JSON to this file.
+    #[arg(long, short)]
+    pub output: Option<String>,
+    /// Don't read or write the scan cache.
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TaxerCommand {
+    /// Rename or merge keywords across the repo.
+    Rename {
+        /// Renames, like `nodejs=node.js`.
+        pairs: Vec<String>,
+        /// A file of renames, one per line, with a tab between
+        /// the old and new keyword.
+        #[arg(long)]
+        file: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Watch `source/` and re-check pages as they're edited.
+    Watch,
+    /// Accept, reject, or edit each proposed change in turn.
+    Review,
+    /// Check keywords against the approved list, replacing known aliases.
+    Vocab {
+        /// The approved keyword list.
+        /// Defaults to `.codetagger/keywords` in the repo.
+        #[arg(long)]
+        keywords: Option<String>,
+    },
+    /// Count pages by keyword and facet, or list the pages matching a query.
+    Inventory {
+        /// List pages with this keyword.
+        #[arg(long)]
+        keyword: Option<String>,
+        /// List pages with this facet value, as `name=value`.
+        /// A bare value means a programming language.
+        #[arg(long)]
+        facet: Option<String>,
+        /// List pages without any keywords or facets.
+        #[arg(long)]
+        untagged: bool,
+        /// List pages with driver tabs but no programming language facet.
+        #[arg(long)]
+        tabs_without_facet: bool,
+    },
+    /// Write an HTML report of tagging coverage.
+    Report {
+        /// Where to write the report.
+        #[arg(long, short, default_value = "codetagger-report.html")]
+        output: String,
+    },
+    /// Compare what's found from the source with what's found from
+    /// the ASTs given with `--ast`.
+    CrossCheck,
+    /// Compare tagging across the version directories in the repo.
+    Versions,
+}
+
+impl Args {
+    pub fn config(&self, vfs: &dyn Vfs) -> Config {
+        let mut config = Config::for_repo(vfs, &self.repo);
+        if let Some(sources) = &self.lang_sources {
+            config.lang_sources = sources.iter().copied().collect();
+        }
+        config.ast_dir = self.ast.clone();
+        if self.docs_version.is_some() {
+            config.current_version = self.docs_version.clone();
+            config.load_project(vfs, &self.repo);
+        }
+        config
+    }
 }
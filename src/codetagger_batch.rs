@@ -0,0 +1,32 @@
+//! Runs `codetagger` over every repo in a manifest and prints a combined report.
+//! See `codetagger::batch` for the manifest format.
+
+use ansi_term::Colour::White;
+use clap::Parser;
+
+use codetagger::batch::{print_summaries, run_batch, Manifest};
+use codetagger::cli::BatchArgs;
+
+fn main() {
+    let args = BatchArgs::parse();
+    let manifest = Manifest::load(&args.manifest);
+
+    let summaries = run_batch(&manifest, args.dryrun, args.no_cache);
+    print_summaries(&summaries);
+
+    if let Some(output) = &args.output {
+        let json = serde_json::to_string_pretty(&summaries).expect("Unable to serialize report");
+        std::fs::write(output, json).expect("Unable to write report");
+    }
+
+    if args.dryrun {
+        println!(
+            "{}",
+            White.paint("\n👉 This was a dry run.\nTo update files, run with `--dryrun=false`.")
+        );
+    }
+
+    if summaries.iter().any(|s| s.error.is_some()) {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,168 @@
+//! Settings that change what the detectors find.
+//!
+//! Besides command-line flags, settings can be put in
+//! `.codetagger/config.toml` in the docs repo:
+//!
+//! ```toml
+//! lang-sources = ["tabs", "code-block"]
+//! # Only run these detectors (see `DETECTOR_IDS`).
+//! detectors = ["code-example", "languages"]
+//! # For repos with a directory per version, the one to tag.
+//! current-version = "current"
+//! # A checkout of the repo `.. sharedinclude::` paths point into.
+//! shared-dir = "../docs-shared"
+//!
+//! # Which directives make a page need a "code example" keyword.
+//! [code-examples]
+//! code-block = true
+//! procedure = false
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CACHE_DIR;
+use crate::snooty::Project;
+use crate::types::Reason;
+use crate::versions::current_root;
+use crate::vfs::Vfs;
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Directives that count as a code example unless configured otherwise.
+/// `procedure` only counts if there's code in one of its steps.
+pub const DEFAULT_CODE_EXAMPLE_DIRECTIVES: &[(&str, bool)] = &[
+    ("code", false),
+    ("code-block", false),
+    ("io-code-block", true),
+    ("literalinclude", true),
+    ("procedure", true),
+    ("sourcecode", false),
+];
+
+/// Where on a page we look for programming languages.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum LangSource {
+    /// `:tabid:` values on pages with `tabs-drivers`.
+    Tabs,
+    /// The argument of `code-block`, `code` and `sourcecode` directives.
+    CodeBlock,
+    /// `:language:` options, as used by `literalinclude` and `io-code-block`.
+    LanguageOption,
+    /// File extensions of `literalinclude` and `io-code-block` `input` paths.
+    Literalinclude,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Config {
+    pub lang_sources: BTreeSet<LangSource>,
+    /// `{+name+}` constants from `snooty.toml`.
+    pub constants: BTreeMap<String, String>,
+    /// A local checkout of the shared repo `.. sharedinclude::` paths are in.
+    /// (`sharedinclude_root` in `snooty.toml` is a URL, so we can't use that.)
+    pub shared_dir: Option<String>,
+    /// Read pages from Snooty ASTs in this directory, where there are any.
+    pub ast_dir: Option<String>,
+    /// Whether each directive counts as a code example.
+    pub code_example_directives: BTreeMap<String, bool>,
+    /// The detectors to run. `None` means all of them.
+    pub detectors: Option<BTreeSet<String>>,
+    /// For versioned repos, the version directory to tag.
+    pub current_version: Option<String>,
+}
+
+/// Settings that can be set in `.codetagger/config.toml`
+/// (or for each repo in a batch manifest).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfigOverrides {
+    pub lang_sources: Option<BTreeSet<LangSource>>,
+    #[serde(default)]
+    pub code_examples: BTreeMap<String, bool>,
+    pub detectors: Option<BTreeSet<String>>,
+    pub current_version: Option<String>,
+    /// Relative to the docs repo.
+    pub shared_dir: Option<String>,
+}
+
+impl ConfigOverrides {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(sources) = &self.lang_sources {
+            config.lang_sources = sources.clone();
+        }
+        config
+            .code_example_directives
+            .extend(self.code_examples.clone());
+        if let Some(detectors) = &self.detectors {
+            config.detectors = Some(detectors.clone());
+        }
+        if let Some(version) = &self.current_version {
+            config.current_version = Some(version.clone());
+        }
+        if let Some(dir) = &self.shared_dir {
+            config.shared_dir = Some(dir.clone());
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            lang_sources: LangSource::value_variants().iter().copied().collect(),
+            constants: BTreeMap::default(),
+            shared_dir: None,
+            ast_dir: None,
+            code_example_directives: DEFAULT_CODE_EXAMPLE_DIRECTIVES
+                .iter()
+                .map(|(name, counts)| (name.to_string(), *counts))
+                .collect(),
+            detectors: None,
+            current_version: None,
+        }
+    }
+}
+
+impl Config {
+    /// The default config, plus whatever `repo`'s `snooty.toml`
+    /// and `.codetagger/config.toml` say.
+    pub fn for_repo(vfs: &dyn Vfs, repo: &str) -> Config {
+        let mut config = Config::default();
+        let path = Path::new(repo).join(CACHE_DIR).join(CONFIG_FILE);
+        if let Ok(contents) = vfs.read(&path.to_string_lossy()) {
+            match toml::from_str::<ConfigOverrides>(&contents) {
+                Ok(mut overrides) => {
+                    overrides.shared_dir = (overrides.shared_dir)
+                        .map(|dir| String::from(Path::new(repo).join(dir).to_string_lossy()));
+                    overrides.apply(&mut config)
+                }
+                Err(e) => eprintln!("Ignoring {}: {e}", path.display()),
+            }
+        }
+        config.load_project(vfs, repo);
+        config
+    }
+
+    /// Take constants and the like from the `snooty.toml` of `repo`
+    /// (or of its current version, if it's versioned). A missing current
+    /// version is left for `Repo` to report.
+    pub fn load_project(&mut self, vfs: &dyn Vfs, repo: &str) {
+        let project = current_root(vfs, repo, self.current_version.as_deref())
+            .ok()
+            .and_then(|root| Project::load(vfs, &root))
+            .unwrap_or_default();
+        self.constants = project.constant_strings();
+    }
+
+    /// Whether the detector that finds `reason` is turned on.
+    pub fn detects(&self, reason: &Reason) -> bool {
+        self.detectors
+            .as_ref()
+            .is_none_or(|detectors| detectors.contains(reason.id()))
+    }
+}
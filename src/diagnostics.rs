@@ -0,0 +1,209 @@
+//! Comparing the tags a page has with the tags it should have.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use ansi_term::Colour::{Red, Yellow};
+use itertools::Itertools;
+
+use crate::meta::{meta_keywords_from_lines, pl_facet_values_from_str};
+use crate::types::{Language, Reason, CODE_EXAMPLE_KIND_KEYWORDS};
+
+/// The tags a page should have, based on its Reasons.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ExpectedTags {
+    pub keywords: BTreeSet<String>,
+    pub languages: BTreeSet<Language>,
+}
+
+impl ExpectedTags {
+    pub fn from_reasons<'a>(reasons: impl IntoIterator<Item = &'a Reason>) -> ExpectedTags {
+        let mut expected = ExpectedTags::default();
+        for reason in reasons {
+            expected
+                .keywords
+                .extend(reason.keywords().into_iter().map(String::from));
+            if let Reason::Languages(langs) = reason {
+                expected.languages.extend(langs.iter().cloned());
+            }
+        }
+        // Only the most involved kind of code example gets its keyword.
+        if let Some(kind) = CODE_EXAMPLE_KIND_KEYWORDS
+            .iter()
+            .rposition(|k| expected.keywords.contains(*k))
+        {
+            let best = CODE_EXAMPLE_KIND_KEYWORDS[kind];
+            expected
+                .keywords
+                .retain(|k| k == best || !CODE_EXAMPLE_KIND_KEYWORDS.contains(&k.as_str()));
+        }
+        expected
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticKind {
+    MissingKeyword(String),
+    MissingLanguages(BTreeSet<Language>),
+    StaleLanguages(BTreeSet<Language>),
+}
+
+/// A problem with the tags on a page.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub path: String,
+    /// 0-based line the problem should be reported on.
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    pub fn message(&self) -> String {
+        match &self.kind {
+            DiagnosticKind::MissingKeyword(k) => format!("page is missing the `{k}` keyword"),
+            DiagnosticKind::MissingLanguages(langs) => format!(
+                "programming_language facet is missing {}",
+                langs.iter().format(", ")
+            ),
+            DiagnosticKind::StaleLanguages(langs) => format!(
+                "programming_language facet has {} but no code for it was found",
+                langs.iter().format(", ")
+            ),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self.kind {
+            DiagnosticKind::StaleLanguages(_) => Yellow.paint("stale"),
+            _ => Red.paint("missing"),
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.path,
+            self.line + 1,
+            label,
+            self.message()
+        )
+    }
+}
+
+/// Check a page's `contents` against the tags it should have.
+pub fn diagnose(path: &str, contents: &str, expected: &ExpectedTags) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    if path.contains("/includes/") {
+        return diagnostics;
+    }
+
+    let lines: Vec<String> = contents.lines().map(String::from).collect();
+    let line_of = |s: &str| lines.iter().position(|l| l.contains(s)).unwrap_or(0);
+
+    let keywords = meta_keywords_from_lines(&lines).unwrap_or_default();
+    for keyword in &expected.keywords {
+        if !keywords.contains(keyword) {
+            diagnostics.push(Diagnostic {
+                path: path.to_string(),
+                line: line_of(":keywords:"),
+                kind: DiagnosticKind::MissingKeyword(keyword.clone()),
+            });
+        }
+    }
+
+    let facet = pl_facet_values_from_str(contents).unwrap_or_default();
+    let facet_line = line_of(":name: programming_language");
+    let missing: BTreeSet<Language> = expected.languages.difference(&facet).cloned().collect();
+    if !missing.is_empty() {
+        diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            line: facet_line,
+            kind: DiagnosticKind::MissingLanguages(missing),
+        });
+    }
+    let stale: BTreeSet<Language> = facet.difference(&expected.languages).cloned().collect();
+    if !stale.is_empty() {
+        diagnostics.push(Diagnostic {
+            path: path.to_string(),
+            line: facet_line,
+            kind: DiagnosticKind::StaleLanguages(stale),
+        });
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CodeExampleKind;
+
+    const PAGE: &str = "\
+.. meta::
+   :keywords: code example
+
+.. facet::
+   :name: programming_language
+   :values: go, java
+
+Title
+";
+
+    fn expected(keywords: &[&str], languages: &[Language]) -> ExpectedTags {
+        ExpectedTags {
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            languages: languages.iter().cloned().collect(),
+        }
+    }
+
+    #[test]
+    fn nothing_to_report() {
+        let expected = expected(&["code example"], &[Language::Go, Language::Java]);
+        assert!(diagnose("/docs/source/page.txt", PAGE, &expected).is_empty());
+    }
+
+    #[test]
+    fn missing_and_stale_tags() {
+        let expected = expected(
+            &["code example", "pymongo"],
+            &[Language::Go, Language::Python],
+        );
+        let kinds: Vec<DiagnosticKind> = diagnose("/docs/source/page.txt", PAGE, &expected)
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                DiagnosticKind::MissingKeyword(String::from("pymongo")),
+                DiagnosticKind::MissingLanguages(BTreeSet::from([Language::Python])),
+                DiagnosticKind::StaleLanguages(BTreeSet::from([Language::Java])),
+            ]
+        );
+    }
+
+    #[test]
+    fn diagnostics_are_on_the_tag_lines() {
+        let expected = expected(&["pymongo"], &[Language::Python]);
+        let diagnostics = diagnose("/docs/source/page.txt", PAGE, &expected);
+        let lines: Vec<usize> = diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, [1, 4, 4]);
+    }
+
+    #[test]
+    fn includes_arent_diagnosed() {
+        let expected = expected(&["pymongo"], &[Language::Python]);
+        assert!(diagnose("/docs/source/includes/page.rst", PAGE, &expected).is_empty());
+    }
+
+    #[test]
+    fn only_the_most_involved_kind_is_expected() {
+        let reasons = [
+            Reason::CodeExample(CodeExampleKind::Snippet, String::from("code-block::")),
+            Reason::CodeExample(CodeExampleKind::Tabbed, String::from("tabs-drivers::")),
+        ];
+        let expected = ExpectedTags::from_reasons(&reasons);
+        assert!(expected.keywords.contains("tabbed example"));
+        assert!(!expected.keywords.contains("code snippet"));
+    }
+}
@@ -1,12 +1,21 @@
 //! Functions for working with files.
 
 use std::collections::BTreeSet;
-use std::fs::read_to_string;
 
 use itertools::Itertools;
-use regex::Regex;
-
-use crate::types::Language;
+use regex::{NoExpand, Regex};
+
+use crate::meta::{get_meta_keywords, meta_keywords_from_lines};
+use crate::suppressions::{ignores_tag, parse_ignore_directives, Suppression};
+use crate::types::{Language, CODE_EXAMPLE_KIND_KEYWORDS};
+use crate::vfs::Vfs;
+
+/// Whether `path` is a page, rather than an include or some other file.
+pub fn is_page(path: &str) -> bool {
+    path.contains("/source/")
+        && !path.contains("/includes/")
+        && (path.ends_with(".txt") || path.ends_with(".rst"))
+}
 
 macro_rules! dont_edit_includes_direct {
     ($path:expr) => {
@@ -19,97 +28,215 @@ macro_rules! dont_edit_includes_direct {
     };
 }
 
-pub fn add_to_meta_keywords(path: &str, keyword: &str, dryrun: bool) {
+pub fn add_to_meta_keywords(vfs: &dyn Vfs, path: &str, keyword: &str, dryrun: bool) {
     dont_edit_includes_direct!(path);
 
-    let contents = read_to_string(path).expect("oops");
+    let contents = vfs.read(path).expect("oops");
 
-    let re = Regex::new(r"(.*):keywords:(.*)").unwrap();
-    let r = re.find(&contents);
-
-    if r.is_some() {
-        let rmatch = r.unwrap().as_str();
-        // Need to convert `$` to ``$$`` otherwise strings like `$vectorSearch`
-        // disappear when we do the replacement.
-        // According to the regex crate docs, "To write a literal $ use $$"
-        // (https://docs.rs/regex/1.10.4/regex/struct.Regex.html#replacement-string-syntax).
-        let rmatch = rmatch.replace('$', "$$");
-
-        let mut needs_comma = true;
-        if rmatch.ends_with(":keywords:") {
-            needs_comma = false
+    if let Some(newcontents) = insert_meta_keyword(&contents, keyword) {
+        if !dryrun {
+            vfs.write(path, &newcontents).expect("Unable to write file");
         }
-        let newstring = if needs_comma {
-            rmatch + ", " + keyword
-        } else {
-            rmatch + " " + keyword
-        };
+        println!("✓ File edited: {path}");
+    }
+}
+
+/// Add `keyword` to the `:keywords:` line in `contents`.
+/// Returns `None` if there is no `:keywords:` line.
+pub fn insert_meta_keyword(contents: &str, keyword: &str) -> Option<String> {
+    // Pages only get the keyword for one kind of code example,
+    // so a new kind replaces the old one.
+    if is_kind_keyword(keyword) {
+        let lines: Vec<String> = contents.lines().map(String::from).collect();
+        let mut keywords = meta_keywords_from_lines(&lines)?;
+        keywords.retain(|k| !is_kind_keyword(k));
+        keywords.push(keyword.to_string());
+        return replace_meta_keywords(contents, &keywords);
+    }
+
+    let re = Regex::new(r"(.*):keywords:(.*)").unwrap();
+    let r = re.find(contents)?;
+
+    let rmatch = r.as_str();
+    // Need to convert `$` to ``$$`` otherwise strings like `$vectorSearch`
+    // disappear when we do the replacement.
+    // According to the regex crate docs, "To write a literal $ use $$"
+    // (https://docs.rs/regex/1.10.4/regex/struct.Regex.html#replacement-string-syntax).
+    let rmatch = rmatch.replace('$', "$$");
+
+    let mut needs_comma = true;
+    if rmatch.ends_with(":keywords:") {
+        needs_comma = false
+    }
+    let newstring = if needs_comma {
+        rmatch + ", " + keyword
+    } else {
+        rmatch + " " + keyword
+    };
+
+    Some(re.replace(contents, newstring).to_string())
+}
 
-        let newcontents: String = re.replace(&contents, newstring).to_string();
+pub fn set_meta_keywords(vfs: &dyn Vfs, path: &str, keywords: &[String], dryrun: bool) {
+    dont_edit_includes_direct!(path);
+
+    let contents = vfs.read(path).expect("oops");
+
+    if let Some(newcontents) = replace_meta_keywords(&contents, keywords) {
+        if newcontents == contents {
+            return;
+        }
         if !dryrun {
-            std::fs::write(path, newcontents).expect("Unable to write file");
+            vfs.write(path, &newcontents).expect("Unable to write file");
         }
         println!("✓ File edited: {path}");
     }
 }
 
-pub fn add_meta_keywords(path: &str, dryrun: bool) {
+/// Replace the `:keywords:` line in `contents` with `keywords`,
+/// dropping any duplicates. Returns `None` if there is no `:keywords:` line.
+pub fn replace_meta_keywords(contents: &str, keywords: &[String]) -> Option<String> {
+    let re = Regex::new(r"(?m)^([ \t]*):keywords:.*$").unwrap();
+    let indent = re.captures(contents)?[1].to_string();
+
+    let keywords: Vec<&String> = keywords.iter().unique().collect();
+    let newstring = if keywords.is_empty() {
+        format!("{indent}:keywords:")
+    } else {
+        format!("{indent}:keywords: {}", keywords.iter().format(", "))
+    };
+
+    Some(re.replacen(contents, 1, NoExpand(&newstring)).to_string())
+}
+
+pub fn add_meta_keywords(vfs: &dyn Vfs, path: &str, dryrun: bool) {
     dont_edit_includes_direct!(path);
 
-    let mut contents = read_to_string(path).expect("oops");
-    contents.insert_str(0, ".. meta::\n   :keywords:\n\n");
+    let contents = vfs.read(path).expect("oops");
+    let contents = insert_meta_keywords(&contents);
     if !dryrun {
-        std::fs::write(path, contents).expect("Unable to write file");
+        vfs.write(path, &contents).expect("Unable to write file");
     }
     println!("✓ File edited: {path}");
 }
 
-pub fn add_pl_facet(path: &str, dryrun: bool, langs: BTreeSet<Language>) {
+/// Add an empty meta keywords directive to the top of `contents`.
+pub fn insert_meta_keywords(contents: &str) -> String {
+    String::from(".. meta::\n   :keywords:\n\n") + contents
+}
+
+pub fn add_pl_facet(vfs: &dyn Vfs, path: &str, dryrun: bool, mut langs: BTreeSet<Language>) {
     dont_edit_includes_direct!(path);
 
+    let contents = vfs.read(path).expect("oops");
+
+    let ignores = parse_ignore_directives(&read_lines(vfs, path));
+    langs.retain(|l| !ignores_tag(&ignores, &Suppression::Language(l.clone())));
+    if langs.is_empty() {
+        println!("⏭ Skipping {path} (codetagger: ignore)");
+        return;
+    }
+
+    let contents = insert_pl_facet(&contents, &langs);
+
+    if !dryrun {
+        vfs.write(path, &contents).expect("Unable to write file");
+    }
+    println!("✓ File edited: {path}");
+}
+
+/// Replace any programming language facets in `contents` with one for `langs`.
+pub fn insert_pl_facet(contents: &str, langs: &BTreeSet<Language>) -> String {
     // Remove any duplicates
-    let mut string = read_to_string(path).unwrap();
-    while string.contains(&String::from(":name: programming_language")) {
-        rm_pl_facet(path, dryrun);
-        string = read_to_string(path).unwrap();
+    let mut contents = contents.to_string();
+    while let Some(newcontents) = remove_pl_facet(&contents) {
+        contents = newcontents;
     }
 
     let mut facet = String::from(".. facet::\n   :name: programming_language\n   :values: ");
     facet += &format!("{}", langs.iter().format(", "));
     facet += "\n\n";
 
-    let mut contents = read_to_string(path).expect("oops");
     contents.insert_str(0, &facet);
-
-    if !dryrun {
-        std::fs::write(path, contents).expect("Unable to write file");
-    }
-    println!("✓ File edited: {path}");
+    contents
 }
 
-pub fn rm_pl_facet(path: &str, dryrun: bool) {
+pub fn rm_pl_facet(vfs: &dyn Vfs, path: &str, dryrun: bool) {
     dont_edit_includes_direct!(path);
 
-    let contents = read_to_string(path).expect("oops");
+    let contents = vfs.read(path).expect("oops");
+
+    if let Some(newcontents) = remove_pl_facet(&contents) {
+        if !dryrun {
+            vfs.write(path, &newcontents).expect("Unable to write file");
+        }
+    }
+}
+
+/// Remove the first programming language facet from `contents`.
+/// Returns `None` if there isn't one.
+pub fn remove_pl_facet(contents: &str) -> Option<String> {
     let re = Regex::new(
         r"\.\. facet::(.*)\n(.*):name: programming_language(.*)\n.(.*):values:(.*)(\n*)",
     )
     .unwrap();
-    let r = re.find(&contents);
+    re.find(contents)?;
 
-    if r.is_some() {
-        let newstring = "";
-        let newcontents: String = re.replace(&contents, newstring).to_string();
-        if !dryrun {
-            std::fs::write(path, newcontents).expect("Unable to write file");
-        }
+    let newstring = "";
+    Some(re.replace(contents, newstring).to_string())
+}
+
+pub fn tag_with_keyword(vfs: &dyn Vfs, file: &str, s: &str, dryrun: bool) {
+    let ignores = parse_ignore_directives(&read_lines(vfs, file));
+    if ignores_tag(&ignores, &Suppression::Keyword(s.to_string())) {
+        println!("⏭ Skipping {file} (codetagger: ignore)");
+        return;
+    }
+
+    let meta_keywords: Option<Vec<String>> = get_meta_keywords(vfs, file);
+
+    // File doesn't have any meta keywords.
+    // Add them! (But skip includes.)
+    if meta_keywords.is_none() && !file.contains("/includes/") {
+        add_meta_keywords(vfs, file, dryrun);
     }
+
+    let already_tagged = meta_keywords.is_some_and(|keys| {
+        keys.contains(&String::from(s))
+            && !(is_kind_keyword(s) && keys.iter().any(|k| k != s && is_kind_keyword(k)))
+    });
+    if !already_tagged && !file.contains("/includes/") {
+        add_to_meta_keywords(vfs, file, s, dryrun)
+    }
+}
+
+/// Whether `keyword` is one of the code example kind keywords.
+pub fn is_kind_keyword(keyword: &str) -> bool {
+    CODE_EXAMPLE_KIND_KEYWORDS.contains(&keyword)
 }
 
-pub fn read_lines(filename: &str) -> Vec<String> {
-    read_to_string(filename)
+pub fn read_lines(vfs: &dyn Vfs, filename: &str) -> Vec<String> {
+    vfs.read(filename)
         .unwrap_or_default() // panic on possible file-reading errors
         .lines() // split the string into an iterator of string slices
         .map(String::from) // make each slice into a string
         .collect() // gather them together into a vector
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_kind_keyword_replaces_old_one() {
+        let contents = ".. meta::\n   :keywords: atlas, code snippet, code example\n\nPage\n";
+        assert_eq!(
+            insert_meta_keyword(contents, "tabbed example").unwrap(),
+            ".. meta::\n   :keywords: atlas, code example, tabbed example\n\nPage\n"
+        );
+        assert_eq!(
+            insert_meta_keyword(contents, "java sync").unwrap(),
+            ".. meta::\n   :keywords: atlas, code snippet, code example, java sync\n\nPage\n"
+        );
+    }
+}
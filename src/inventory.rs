@@ -0,0 +1,107 @@
+//! An index of which pages have which keywords and facets.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ansi_term::Colour::White;
+
+use crate::cache::Cache;
+use crate::files::is_page;
+
+/// The facet a bare `--facet` value refers to.
+pub const DEFAULT_FACET: &str = "programming_language";
+
+#[derive(Debug, Default)]
+pub struct Inventory {
+    pub pages: BTreeSet<String>,
+    /// Each keyword, and the pages that have it.
+    pub keywords: BTreeMap<String, BTreeSet<String>>,
+    /// Each facet name and value, and the pages that have it.
+    pub facets: BTreeMap<(String, String), BTreeSet<String>>,
+    /// Pages without any keywords or facets.
+    pub untagged: BTreeSet<String>,
+    /// Pages with driver tabs (of their own or included)
+    /// but no programming language facet.
+    pub tabs_without_facet: BTreeSet<String>,
+}
+
+impl Inventory {
+    pub fn build(cache: &Cache) -> Inventory {
+        let mut inventory = Inventory::default();
+        for (path, entry) in cache.entries().filter(|(path, _)| is_page(path)) {
+            inventory.pages.insert(path.clone());
+
+            let keywords = entry.keywords.clone().unwrap_or_default();
+            for keyword in &keywords {
+                inventory
+                    .keywords
+                    .entry(keyword.clone())
+                    .or_default()
+                    .insert(path.clone());
+            }
+            for (name, values) in &entry.facet_values {
+                for value in values {
+                    inventory
+                        .facets
+                        .entry((name.clone(), value.clone()))
+                        .or_default()
+                        .insert(path.clone());
+                }
+            }
+
+            if keywords.is_empty() && entry.facet_values.is_empty() {
+                inventory.untagged.insert(path.clone());
+            }
+
+            let has_tabs = entry.code_tabs.is_some()
+                || cache
+                    .transitive_includes(path)
+                    .iter()
+                    .any(|p| cache.entry(p).is_some_and(|e| e.code_tabs.is_some()));
+            if has_tabs && !entry.facet_values.contains_key(DEFAULT_FACET) {
+                inventory.tabs_without_facet.insert(path.clone());
+            }
+        }
+        inventory
+    }
+
+    /// Pages with `keyword`.
+    pub fn with_keyword(&self, keyword: &str) -> BTreeSet<String> {
+        self.keywords.get(keyword).cloned().unwrap_or_default()
+    }
+
+    /// Pages with a facet value, given as `name=value`,
+    /// or just `value` for the programming language facet.
+    pub fn with_facet(&self, query: &str) -> BTreeSet<String> {
+        let (name, value) = query.split_once('=').unwrap_or((DEFAULT_FACET, query));
+        self.facets
+            .get(&(name.trim().to_string(), value.trim().to_string()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn print_summary(&self) {
+        println!("{}", White.bold().paint("Keywords"));
+        for (keyword, pages) in sorted_by_count(&self.keywords) {
+            println!("  {:>5}  {keyword}", pages.len());
+        }
+
+        println!("\n{}", White.bold().paint("Facets"));
+        for ((name, value), pages) in sorted_by_count(&self.facets) {
+            println!("  {:>5}  {name}: {value}", pages.len());
+        }
+
+        println!("\n{}", White.bold().paint("Pages"));
+        println!("  {:>5}  total", self.pages.len());
+        println!("  {:>5}  untagged", self.untagged.len());
+        println!(
+            "  {:>5}  with driver tabs but no {DEFAULT_FACET} facet",
+            self.tabs_without_facet.len()
+        );
+    }
+}
+
+fn sorted_by_count<K: Ord>(index: &BTreeMap<K, BTreeSet<String>>) -> Vec<(&K, &BTreeSet<String>)> {
+    let mut sorted: Vec<_> = index.iter().collect();
+    sorted.sort_by_key(|(_, pages)| std::cmp::Reverse(pages.len()));
+    sorted
+}
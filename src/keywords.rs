@@ -0,0 +1,251 @@
+//! Finding keywords that are spelled differently across the repo
+//! but mean the same thing.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::read_to_string;
+
+use itertools::Itertools;
+
+use crate::cache::Cache;
+use crate::files::set_meta_keywords;
+
+/// Every keyword in the repo, and the pages that use it.
+pub fn keyword_uses(cache: &Cache) -> BTreeMap<String, Vec<String>> {
+    let mut uses: BTreeMap<String, Vec<String>> = BTreeMap::default();
+    for (path, entry) in cache.entries() {
+        for keyword in entry.keywords.iter().flatten() {
+            uses.entry(keyword.clone()).or_default().push(path.clone());
+        }
+    }
+    uses
+}
+
+/// Lowercase `keyword` and drop punctuation and spaces,
+/// so `Node.js`, `nodejs` and `node js` all come out the same.
+// Plurals are left to `similar`, since stripping an `s`
+// mangles names like `ios`, `aws` and `atlas`.
+pub fn normalize(keyword: &str) -> String {
+    keyword
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+/// Whether `a` and `b` look like the same keyword.
+pub fn similar(a: &str, b: &str, max_dist: usize) -> bool {
+    let (a, b) = (normalize(a), normalize(b));
+    if a == b {
+        return true;
+    }
+    // Short keywords like `go` and `c` are always a few edits apart,
+    // so only allow one edit per four characters.
+    let dist = edit_distance::edit_distance(&a, &b);
+    dist <= max_dist && dist * 4 <= a.len().min(b.len())
+}
+
+/// A group of keywords that look like the same thing.
+#[derive(Debug)]
+pub struct Cluster {
+    /// Each spelling, most used first, and the pages that use it.
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+impl Cluster {
+    /// The spelling we'd suggest everyone use:
+    /// the most common one, preferring lowercase.
+    pub fn canonical(&self) -> &str {
+        // `max_by_key` returns the last of equals, so reverse to get the first.
+        self.variants
+            .iter()
+            .rev()
+            .max_by_key(|(k, pages)| (pages.len(), k.to_lowercase() == *k))
+            .map(|(k, _)| k.as_str())
+            .unwrap_or_default()
+    }
+}
+
+/// Group near-duplicate keywords in `uses`.
+/// Keywords without any look-alikes aren't returned.
+pub fn clusters(uses: &BTreeMap<String, Vec<String>>, max_dist: usize) -> Vec<Cluster> {
+    let keywords: Vec<&String> = uses.keys().collect();
+
+    // Union-find over the keywords, joining each similar pair once.
+    let mut parent: Vec<usize> = (0..keywords.len()).collect();
+    fn root(parent: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for (i, j) in (0..keywords.len()).tuple_combinations() {
+        if similar(keywords[i], keywords[j], max_dist) {
+            let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+            parent[ri] = rj;
+        }
+    }
+
+    let mut groups: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::default();
+    for i in 0..keywords.len() {
+        groups.entry(root(&mut parent, i)).or_default().insert(i);
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|group| {
+            let variants = group
+                .into_iter()
+                .map(|i| (keywords[i].clone(), uses[keywords[i]].clone()))
+                .sorted_by_key(|(k, pages)| (std::cmp::Reverse(pages.len()), k.clone()))
+                .collect();
+            Cluster { variants }
+        })
+        .collect();
+    clusters
+        .sort_by_key(|c| std::cmp::Reverse(c.variants.iter().map(|(_, p)| p.len()).sum::<usize>()));
+    clusters
+}
+
+/// Read renames from `old=new` pairs and from `file`,
+/// which has one `old<TAB>new` per line.
+pub fn parse_renames(pairs: &[String], file: Option<&str>) -> BTreeMap<String, String> {
+    let mut renames: BTreeMap<String, String> = BTreeMap::default();
+    for pair in pairs {
+        let (old, new) = pair
+            .split_once('=')
+            .unwrap_or_else(|| panic!("Expected `old=new`, got `{pair}`"));
+        renames.insert(old.trim().to_string(), new.trim().to_string());
+    }
+    if let Some(file) = file {
+        let contents =
+            read_to_string(file).unwrap_or_else(|_| panic!("Unable to read renames {file}"));
+        for line in contents.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let Some((old, new)) = line.split_once('\t') else {
+                eprintln!("Ignoring malformed rename: {line}");
+                continue;
+            };
+            renames.insert(old.trim().to_string(), new.trim().to_string());
+        }
+    }
+    renames
+}
+
+/// Rename keywords on every page according to `renames`,
+/// dropping any duplicates that leaves. Returns how many pages changed.
+pub fn rename_keywords(cache: &Cache, renames: &BTreeMap<String, String>, dryrun: bool) -> usize {
+    let mut count = 0;
+    for (path, entry) in cache.entries() {
+        let Some(keywords) = &entry.keywords else {
+            continue;
+        };
+        if !keywords.iter().any(|k| renames.contains_key(k)) {
+            continue;
+        }
+        if path.contains("/includes/") {
+            println!("⏭ Skipping {path} (includes can't be edited)");
+            continue;
+        }
+        let renamed: Vec<String> = keywords
+            .iter()
+            .map(|k| renames.get(k).unwrap_or(k).clone())
+            .collect();
+        set_meta_keywords(cache.vfs(), path, &renamed, dryrun);
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::vfs::{MemoryFs, Vfs};
+
+    fn uses(keywords: &[(&str, usize)]) -> BTreeMap<String, Vec<String>> {
+        keywords
+            .iter()
+            .map(|(k, n)| {
+                (
+                    k.to_string(),
+                    (0..*n).map(|i| format!("page{i}.txt")).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn similar_keywords() {
+        assert!(similar("Node.js", "node js", 0));
+        assert!(similar("aggregation", "agregation", 1));
+        assert!(similar("transactions", "transaction", 1));
+        assert!(!similar("go", "c", 2));
+        assert!(!similar("ios", "aws", 2));
+    }
+
+    #[test]
+    fn clusters_join_look_alikes() {
+        let uses = uses(&[
+            ("Node.js", 1),
+            ("nodejs", 3),
+            ("node js", 1),
+            ("aggregation", 2),
+            ("agregation", 1),
+            ("go", 4),
+        ]);
+        let clusters = clusters(&uses, 1);
+        let names: Vec<Vec<&str>> = clusters
+            .iter()
+            .map(|c| c.variants.iter().map(|(k, _)| k.as_str()).collect())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                vec!["nodejs", "Node.js", "node js"],
+                vec!["aggregation", "agregation"],
+            ]
+        );
+    }
+
+    #[test]
+    fn canonical_prefers_most_used_then_lowercase() {
+        let cluster = |variants: &[(&str, usize)]| Cluster {
+            variants: uses(variants).into_iter().collect(),
+        };
+        assert_eq!(
+            cluster(&[("Node.js", 1), ("nodejs", 3)]).canonical(),
+            "nodejs"
+        );
+        assert_eq!(cluster(&[("Atlas", 2), ("atlas", 2)]).canonical(), "atlas");
+        assert_eq!(cluster(&[("Atlas", 2), ("atlas", 1)]).canonical(), "Atlas");
+    }
+
+    #[test]
+    fn renames_drop_duplicates() {
+        const PAGE: &str = "/docs/source/page.txt";
+        let vfs = Arc::new(MemoryFs::new([(
+            PAGE.to_string(),
+            String::from(".. meta::\n   :keywords: Node.js, nodejs, aggregation\n"),
+        )]));
+        let mut cache = Cache::in_memory(&Config::default()).with_vfs(vfs.clone());
+        cache.scan("/docs").unwrap();
+
+        let renames = parse_renames(&[String::from(" Node.js = nodejs ")], None);
+        assert_eq!(
+            renames,
+            BTreeMap::from([(String::from("Node.js"), String::from("nodejs"))])
+        );
+        assert_eq!(rename_keywords(&cache, &renames, false), 1);
+        assert_eq!(
+            vfs.read(PAGE).unwrap(),
+            ".. meta::\n   :keywords: nodejs, aggregation\n"
+        );
+    }
+}
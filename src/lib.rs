@@ -1,8 +1,25 @@
+pub mod ast;
+pub mod batch;
+pub mod cache;
+pub mod cli;
+pub mod config;
+pub mod diagnostics;
 pub mod files;
-pub mod includes;
+pub mod inventory;
+pub mod keywords;
 pub mod meta;
+pub mod repo;
+pub mod report;
+pub mod review;
+pub mod rst;
+pub mod snooty;
+pub mod suppressions;
 pub mod types;
-pub mod cli;
+pub mod versions;
+pub mod vfs;
+pub mod vocabulary;
+pub mod watch;
+pub mod yaml;
 
 pub const CODE_TABS_STRINGS_1: &str = "tabs-selector:: drivers";
 pub const CODE_TABS_STRINGS_2: &str = "tabs-drivers::";
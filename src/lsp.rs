@@ -0,0 +1,335 @@
+//! A language server that reports missing or stale tags as diagnostics,
+//! with quick fixes that make the same edits `codetagger` would.
+//!
+//! Talks LSP over stdio. The workspace root should be the root of a docs repo.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::io;
+use std::path::Path;
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics, ShowMessage,
+};
+use lsp_types::request::{CodeActionRequest, Request as _};
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, Diagnostic as LspDiagnostic, DiagnosticSeverity,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DidSaveTextDocumentParams, InitializeParams, MessageType, Position, PublishDiagnosticsParams,
+    Range, ServerCapabilities, ShowMessageParams, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextEdit, Url, WorkspaceEdit,
+};
+
+use codetagger::cache::Cache;
+use codetagger::diagnostics::{diagnose, Diagnostic, DiagnosticKind, ExpectedTags};
+use codetagger::files::{
+    insert_meta_keyword, insert_meta_keywords, insert_pl_facet, remove_pl_facet,
+};
+use codetagger::repo::Repo;
+use codetagger::suppressions::Suppressions;
+
+type LspResult<T> = Result<T, Box<dyn Error + Sync + Send>>;
+
+fn main() -> LspResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        ..Default::default()
+    })?;
+    let params: InitializeParams = serde_json::from_value(connection.initialize(capabilities)?)?;
+
+    let root = workspace_root(&params);
+    match Server::new(&root) {
+        Ok(mut server) => server.run(&connection)?,
+        // Stay up to say why there are no diagnostics, rather than crashing.
+        Err(e) => {
+            let message = format!("codetagger: unable to open {root}: {e}");
+            eprintln!("{message}");
+            let params = ShowMessageParams {
+                typ: MessageType::ERROR,
+                message,
+            };
+            connection
+                .sender
+                .send(Message::Notification(Notification::new(
+                    ShowMessage::METHOD.to_string(),
+                    params,
+                )))?;
+            idle(&connection)?;
+        }
+    }
+
+    // The writer thread only stops once every sender is gone.
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+// Turn down every request until the client shuts us down.
+fn idle(connection: &Connection) -> LspResult<()> {
+    for msg in &connection.receiver {
+        if let Message::Request(req) = msg {
+            if connection.handle_shutdown(&req)? {
+                return Ok(());
+            }
+            let response = Response::new_err(
+                req.id,
+                ErrorCode::RequestFailed as i32,
+                String::from("codetagger couldn't open the workspace"),
+            );
+            connection.sender.send(Message::Response(response))?;
+        }
+    }
+    Ok(())
+}
+
+#[allow(deprecated)] // `root_uri`, for clients that don't send workspace folders
+fn workspace_root(params: &InitializeParams) -> String {
+    let uri = params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .map(|folder| folder.uri.clone())
+        .or_else(|| params.root_uri.clone());
+    let path = match uri.and_then(|uri| uri.to_file_path().ok()) {
+        Some(path) => path,
+        None => std::env::current_dir().expect("No workspace root"),
+    };
+    String::from(path.to_string_lossy())
+}
+
+struct Server {
+    repo: Repo,
+    cache: Cache,
+    suppressions: Suppressions,
+    /// Contents of open documents, by path.
+    open: HashMap<String, String>,
+}
+
+impl Server {
+    fn new(path: &str) -> io::Result<Server> {
+        let repo = Repo::open(path)?;
+        let mut cache = repo.cache(false);
+        cache.scan(&repo.root)?;
+        cache.save();
+        eprintln!("codetagger-lsp: scanned {}", repo.root);
+        Ok(Server {
+            suppressions: repo.suppressions(),
+            repo,
+            cache,
+            open: HashMap::default(),
+        })
+    }
+
+    fn run(&mut self, connection: &Connection) -> LspResult<()> {
+        for msg in &connection.receiver {
+            match msg {
+                Message::Request(req) => {
+                    if connection.handle_shutdown(&req)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(req)?;
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(not) => {
+                    for notification in self.handle_notification(not)? {
+                        connection
+                            .sender
+                            .send(Message::Notification(notification))?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, req: Request) -> LspResult<Response> {
+        if req.method != CodeActionRequest::METHOD {
+            return Ok(Response::new_err(
+                req.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unhandled request: {}", req.method),
+            ));
+        }
+        let params: CodeActionParams = serde_json::from_value(req.params)?;
+        Ok(Response::new_ok(req.id, self.code_actions(&params)))
+    }
+
+    fn handle_notification(&mut self, not: Notification) -> LspResult<Vec<Notification>> {
+        let path = match not.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams = serde_json::from_value(not.params)?;
+                let path = to_path(&params.text_document.uri);
+                self.update(&path, params.text_document.text);
+                path
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams = serde_json::from_value(not.params)?;
+                let path = to_path(&params.text_document.uri);
+                // We only ask for full syncs, so the last change has everything.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.update(&path, change.text);
+                }
+                path
+            }
+            DidSaveTextDocument::METHOD => {
+                let params: DidSaveTextDocumentParams = serde_json::from_value(not.params)?;
+                to_path(&params.text_document.uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams = serde_json::from_value(not.params)?;
+                let path = to_path(&params.text_document.uri);
+                self.open.remove(&path);
+                self.cache.get(&path);
+                path
+            }
+            _ => return Ok(vec![]),
+        };
+
+        // Other versions of a versioned repo aren't tagged.
+        if !Path::new(&path).starts_with(&self.repo.root) || !path.contains("/source/") {
+            return Ok(vec![]);
+        }
+        let mut pages = self.cache.transitive_includers(&path);
+        pages.insert(path);
+
+        let mut notifications: Vec<Notification> = vec![];
+        for page in pages {
+            let Ok(uri) = Url::from_file_path(&page) else {
+                continue;
+            };
+            let text = self.text(&page);
+            let diagnostics = self
+                .diagnostics(&page, &text)
+                .iter()
+                .map(|d| to_lsp_diagnostic(d, &text))
+                .collect();
+            let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+            notifications.push(Notification::new(
+                PublishDiagnostics::METHOD.to_string(),
+                params,
+            ));
+        }
+        Ok(notifications)
+    }
+
+    fn update(&mut self, path: &str, text: String) {
+        self.cache.set_contents(path, &text);
+        self.open.insert(path.to_string(), text);
+    }
+
+    fn text(&self, path: &str) -> String {
+        match self.open.get(path) {
+            Some(text) => text.clone(),
+            None => read_to_string(path).unwrap_or_default(),
+        }
+    }
+
+    fn expected(&self, path: &str) -> ExpectedTags {
+        let strings = self.cache.code_example_strings();
+        let mut expected = self.cache.expected_tags(path, &strings);
+        self.suppressions.filter(path, &mut expected);
+        expected
+    }
+
+    fn diagnostics(&self, path: &str, text: &str) -> Vec<Diagnostic> {
+        if !Path::new(path).is_file() && !self.open.contains_key(path) {
+            return vec![];
+        }
+        diagnose(path, text, &self.expected(path))
+    }
+
+    fn code_actions(&self, params: &CodeActionParams) -> Vec<CodeActionOrCommand> {
+        let uri = &params.text_document.uri;
+        let path = to_path(uri);
+        let text = self.text(&path);
+        let expected = self.expected(&path);
+        let lines = params.range.start.line as usize..=params.range.end.line as usize;
+
+        let mut actions: Vec<CodeActionOrCommand> = vec![];
+        for diagnostic in self.diagnostics(&path, &text) {
+            if !lines.contains(&diagnostic.line) {
+                continue;
+            }
+            let (title, newtext) = fix(&diagnostic, &text, &expected);
+            let edit = TextEdit::new(Range::new(Position::new(0, 0), end_of(&text)), newtext);
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title,
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![to_lsp_diagnostic(&diagnostic, &text)]),
+                edit: Some(WorkspaceEdit::new(HashMap::from([(
+                    uri.clone(),
+                    vec![edit],
+                )]))),
+                ..Default::default()
+            }));
+        }
+        actions
+    }
+}
+
+/// The title of the quick fix for `diagnostic`, and the fixed text.
+fn fix(diagnostic: &Diagnostic, text: &str, expected: &ExpectedTags) -> (String, String) {
+    match &diagnostic.kind {
+        DiagnosticKind::MissingKeyword(keyword) => {
+            let newtext = insert_meta_keyword(text, keyword)
+                .or_else(|| insert_meta_keyword(&insert_meta_keywords(text), keyword))
+                .expect("meta keywords were just added");
+            (format!("Add `{keyword}` to meta keywords"), newtext)
+        }
+        DiagnosticKind::MissingLanguages(_) | DiagnosticKind::StaleLanguages(_) => {
+            if expected.languages.is_empty() {
+                let mut newtext = text.to_string();
+                while let Some(t) = remove_pl_facet(&newtext) {
+                    newtext = t;
+                }
+                (String::from("Remove programming_language facet"), newtext)
+            } else {
+                (
+                    String::from("Update programming_language facet"),
+                    insert_pl_facet(text, &expected.languages),
+                )
+            }
+        }
+    }
+}
+
+fn to_lsp_diagnostic(diagnostic: &Diagnostic, text: &str) -> LspDiagnostic {
+    let line = text.lines().nth(diagnostic.line).unwrap_or_default();
+    let severity = match diagnostic.kind {
+        DiagnosticKind::StaleLanguages(_) => DiagnosticSeverity::INFORMATION,
+        _ => DiagnosticSeverity::WARNING,
+    };
+    LspDiagnostic {
+        range: Range::new(
+            Position::new(diagnostic.line as u32, 0),
+            Position::new(diagnostic.line as u32, line.encode_utf16().count() as u32),
+        ),
+        severity: Some(severity),
+        source: Some(String::from("codetagger")),
+        message: diagnostic.message(),
+        ..Default::default()
+    }
+}
+
+fn end_of(text: &str) -> Position {
+    let last = text.rsplit('\n').next().unwrap_or_default();
+    Position::new(
+        text.matches('\n').count() as u32,
+        last.encode_utf16().count() as u32,
+    )
+}
+
+fn to_path(uri: &Url) -> String {
+    match uri.to_file_path() {
+        Ok(path) => String::from(path.to_string_lossy()),
+        Err(_) => uri.path().to_string(),
+    }
+}
@@ -1,141 +1,217 @@
-use std::collections::HashSet;
+use std::collections::BTreeSet;
+use std::io;
+use std::sync::Arc;
 
 use ansi_term::Colour::White;
 use clap::Parser;
-use codetagger::types::JavaSyncness;
-use walkdir::WalkDir;
 
-use codetagger::cli::Args;
-use codetagger::files::*;
-use codetagger::includes::*;
-use codetagger::meta::*;
-use codetagger::types::Reason;
-
-const CODE_TABS_STRINGS_1: &str = "tabs-selector:: drivers";
-const CODE_TABS_STRINGS_2: &str = "tabs-drivers::";
-
-/// A pair of a file path and optional Reason for needed tagging.
-/// This is hashable so that we can have multiple entries per file.
-#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
-struct FileAndReason(String, Option<Reason>);
+use codetagger::ast::cross_check;
+use codetagger::cache::Cache;
+use codetagger::cli::{Args, Command};
+use codetagger::config::Config;
+use codetagger::inventory::Inventory;
+use codetagger::repo::Repo;
+use codetagger::report::write_report;
+use codetagger::review::review;
+use codetagger::suppressions::unused_suppressions;
+use codetagger::versions::{print_version_differences, DEFAULT_CURRENT_VERSION};
+use codetagger::vfs::{DiskFs, GitFs, Vfs};
+use codetagger::vocabulary::{check_vocabulary, Vocabulary};
+use codetagger::watch::watch;
 
 fn main() {
     let args = Args::parse();
-    let _debug = false;
     let dryrun = args.dryrun;
-    let repo = args.repo;
-
-    let mut files_needing_tag_and_reason: HashSet<FileAndReason> = HashSet::default();
-
-    let mut match_string_list: Vec<String> = vec![];
-    let mut includes_with_code_tabs: Vec<String> = get_includes_with_code_tabs(repo.clone());
-    match_string_list.append(&mut includes_with_code_tabs);
-    match_string_list.push(CODE_TABS_STRINGS_1.to_string());
-    match_string_list.push(CODE_TABS_STRINGS_2.to_string());
-
-    if args.verbose {
-        println!("Strings to look for: {:#?}", match_string_list);
-    }
 
-    // Loop through all sub directories looking
-    // for files that need tagging.
-    println!("👀 Looking for files that need tagging...");
-    for entry in WalkDir::new(&repo) {
-        let entry = entry.unwrap();
-        let entry_path = entry.path();
-        if entry_path.is_dir() {
-            continue;
+    let vfs: Arc<dyn Vfs> = match &args.rev {
+        Some(rev) => {
+            if !dryrun {
+                eprintln!("Can't use `--dryrun=false` with `--rev`: git revisions are read-only.");
+                std::process::exit(2);
+            }
+            if matches!(args.command, Some(Command::Watch | Command::Review)) {
+                eprintln!("`watch` and `review` only work on the working tree.");
+                std::process::exit(2);
+            }
+            println!("🔖 Reading {rev}");
+            match GitFs::open(&args.repo, rev) {
+                Ok(vfs) => Arc::new(vfs),
+                Err(e) => {
+                    eprintln!("Unable to read {rev} from {}: {}", args.repo, e.message());
+                    std::process::exit(2);
+                }
+            }
         }
-        let filepath = String::from(entry_path.to_string_lossy());
-
-        let reason = check_needs_code_example_tag(&filepath, match_string_list.clone());
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+        None => Arc::new(DiskFs),
+    };
+    // Versioned repos only get their current version checked.
+    let repo = Repo::with_vfs(&args.repo, args.config(&*vfs), vfs)
+        .unwrap_or_else(|e| unreadable(&args.repo, e));
+    if repo.root != args.repo {
+        println!("📚 Checking {}", repo.root);
+    }
+    let config = &repo.config;
+    // The saved cache is for the working tree, so other revisions get a fresh one.
+    let mut cache = repo.cache(args.no_cache || args.rev.is_some());
+
+    match args.command {
+        Some(Command::Watch) => {
+            cache
+                .scan(&repo.root)
+                .unwrap_or_else(|e| unreadable(&repo.root, e));
+            watch(&repo, &mut cache);
+            return;
         }
-
-        let reason = check_needs_lang_metadata(&filepath);
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+        Some(Command::Review) => {
+            cache
+                .scan(&repo.root)
+                .unwrap_or_else(|e| unreadable(&repo.root, e));
+            review(&repo.path, &cache, dryrun);
+            cache.save();
+            return;
         }
-
-        let reason = check_needs_nodejs_tag(&filepath);
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+        Some(Command::Vocab { keywords }) => {
+            cache
+                .scan(&repo.root)
+                .unwrap_or_else(|e| unreadable(&repo.root, e));
+            let vocabulary = Vocabulary::load(repo.vfs(), &repo.path, keywords.as_deref());
+            println!("📖 Checking keywords against the approved list...");
+            let count = check_vocabulary(&cache, &vocabulary, dryrun);
+            println!("{count} non-approved keyword(s)");
+            cache.save();
+            return;
         }
+        Some(Command::Inventory {
+            keyword,
+            facet,
+            untagged,
+            tabs_without_facet,
+        }) => {
+            cache
+                .scan(&repo.root)
+                .unwrap_or_else(|e| unreadable(&repo.root, e));
+            let inventory = Inventory::build(&cache);
+            let mut queries: Vec<BTreeSet<String>> = vec![];
+            queries.extend(keyword.map(|k| inventory.with_keyword(&k)));
+            queries.extend(facet.map(|f| inventory.with_facet(&f)));
+            if untagged {
+                queries.push(inventory.untagged.clone());
+            }
+            if tabs_without_facet {
+                queries.push(inventory.tabs_without_facet.clone());
+            }
 
-        let reason = check_needs_java_tag(&filepath);
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+            // Pages have to match every query.
+            match queries.into_iter().reduce(|a, b| &a & &b) {
+                Some(pages) => {
+                    for page in &pages {
+                        println!("{page}");
+                    }
+                    println!("{} page(s)", pages.len());
+                }
+                None => inventory.print_summary(),
+            }
+            cache.save();
+            return;
         }
-
-        let reason = check_needs_compass_tag(&filepath);
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+        Some(Command::Report { output }) => {
+            cache
+                .scan(&repo.root)
+                .unwrap_or_else(|e| unreadable(&repo.root, e));
+            write_report(&repo.path, &cache, &output);
+            cache.save();
+            return;
         }
-
-        // Atlas
-        let reason = check_needs_atlas_api_tag(&filepath);
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+        Some(Command::CrossCheck) => {
+            let Some(ast_dir) = args.ast.as_deref() else {
+                eprintln!("`cross-check` needs `--ast <dir>`.");
+                std::process::exit(2);
+            };
+            let mut text = Cache::in_memory(&Config {
+                ast_dir: None,
+                ..config.clone()
+            })
+            .with_vfs(repo.vfs.clone());
+            text.scan(&repo.root)
+                .unwrap_or_else(|e| unreadable(&repo.root, e));
+            cache
+                .scan(&repo.root)
+                .unwrap_or_else(|e| unreadable(&repo.root, e));
+            println!("🔀 Comparing source with ASTs in {ast_dir}...");
+            let count = cross_check(&text, &cache, ast_dir);
+            println!("{count} difference(s)");
+            cache.save();
+            return;
         }
-
-        let reason = check_needs_atlas_cli_tag(&filepath);
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+        Some(Command::Versions) => {
+            let current = config
+                .current_version
+                .as_deref()
+                .unwrap_or(DEFAULT_CURRENT_VERSION);
+            print_version_differences(repo.vfs.clone(), &args.repo, config, current, args.verbose)
+                .unwrap_or_else(|e| unreadable(&args.repo, e));
+            return;
         }
+        None => {}
+    }
 
-        let reason = check_needs_atlas_ui_tag(&filepath);
-        if reason.is_some() {
-            files_needing_tag_and_reason.insert(FileAndReason(filepath.clone(), reason));
+    // Loop through all sub directories, scanning
+    // (or looking up) every file.
+    println!("👀 Looking for files that need tagging...");
+    let scan = repo
+        .scan(&mut cache)
+        .unwrap_or_else(|e| unreadable(&repo.root, e));
+
+    let unknown_tabids = cache.unknown_tabids();
+    if !unknown_tabids.is_empty() {
+        println!("\n❓ Unknown driver tabids:");
+        for (tabid, locations) in &unknown_tabids {
+            println!("  {:>4}  {tabid}", locations.len());
+            let shown = if args.verbose { locations.len() } else { 3 };
+            for (path, line) in locations.iter().take(shown) {
+                println!("          {path}:{}", line + 1);
+            }
+            if locations.len() > shown {
+                println!("          ...and {} more", locations.len() - shown);
+            }
         }
     }
 
-    if args.verbose {
-        dbg!(&files_needing_tag_and_reason);
+    // Don't edit anything if we're going to fail anyway.
+    if args.fail_on_unknown_tabids && !unknown_tabids.is_empty() {
+        eprintln!(
+            "{} unknown driver tabid(s). Update `Language` to include them.",
+            unknown_tabids.len()
+        );
+        cache.save();
+        std::process::exit(1);
     }
 
-    println!("📝 Tagging for programming language facets ...");
-    let mut already_edited: HashSet<String> = HashSet::default();
-    for FileAndReason(file, reason) in &files_needing_tag_and_reason {
-        if let Some(Reason::Languages(langs)) = reason {
-            if !file.contains("/includes/") {
-                add_pl_facet(file, dryrun, langs.clone());
-                already_edited.insert(file.to_string());
-            }
-
-            let files_that_include_this_file =
-                get_files_that_include_this_file(file.clone(), repo.clone(), args.verbose);
-
-            for file in files_that_include_this_file {
-                if !already_edited.contains(&file) && !file.contains("/includes/") {
-                    add_pl_facet(&file, dryrun, langs.clone());
-                    already_edited.insert(file.to_string());
-                }
+    if args.verbose {
+        println!("Strings to look for: {:#?}", scan.strings);
+        dbg!(&scan.reasons);
+        for (file, expected) in &scan.expected {
+            if !expected.languages.is_empty() {
+                println!("{file} needs languages {:?}", expected.languages);
             }
         }
     }
 
-    for FileAndReason(file, reason) in &files_needing_tag_and_reason {
-        if reason.is_some() {
-            match reason.clone().unwrap() {
-                Reason::CodeExample(_) => tag_with_keyword(file, "code example", dryrun),
-                Reason::NodejsTab => tag_with_keyword(file, "node.js", dryrun),
-                Reason::CompassTab => tag_with_keyword(file, "compass", dryrun),
-                Reason::AtlasApiTab => tag_with_keyword(file, "atlas api", dryrun),
-                Reason::AtlasCliTab => tag_with_keyword(file, "atlas cli", dryrun),
-                Reason::AtlasUiTab => tag_with_keyword(file, "atlas ui", dryrun),
-                Reason::Java(JavaSyncness::Both) => {
-                    tag_with_keyword(file, "java async", dryrun);
-                    tag_with_keyword(file, "java sync", dryrun);
-                }
-                Reason::Java(JavaSyncness::Async) => tag_with_keyword(file, "java async", dryrun),
-                Reason::Java(JavaSyncness::Sync) => tag_with_keyword(file, "java sync", dryrun),
-                Reason::Languages(_) => continue,
-            }
+    println!("📝 Tagging for programming language facets ...");
+    scan.apply(repo.vfs(), dryrun);
+
+    let suppressions = repo.suppressions();
+    let unused = unused_suppressions(&cache, &suppressions);
+    if !unused.is_empty() {
+        println!("\n🤷 Unused suppressions:");
+        for line in unused {
+            println!("  {line}");
         }
     }
 
-    if dryrun {
+    cache.save();
+
+    if dryrun && args.rev.is_none() {
         println!(
             "{}",
             White.paint("\n👉 This was a dry run.\nTo update files, run with `--dryrun=false`.")
@@ -143,39 +219,12 @@ fn main() {
     }
 
     // PANIC if we have two PL facets!
-    for entry in WalkDir::new(&repo) {
-        let entry = entry.unwrap();
-        let entry_path = entry.path();
-        if entry_path.is_dir() {
-            continue;
-        }
-        let filepath = String::from(entry_path.to_string_lossy());
-
-        let lines = read_lines(&filepath);
-        let mut count = 0;
-        for line in lines {
-            if line.contains("programming_language") {
-                count += 1
-            }
-            if count == 2 {
-                panic!("too many PL lines: {filepath}")
-            }
-        }
-    }
+    repo.check_single_pl_facet();
 }
 
-fn tag_with_keyword(file: &str, s: &str, dryrun: bool) {
-    let meta_keywords: Option<Vec<String>> = get_meta_keywords(file);
-
-    // File doesn't have any meta keywords.
-    // Add them! (But skip includes.)
-    if meta_keywords.is_none() && !file.contains("/includes/") {
-        add_meta_keywords(file, dryrun);
-    }
-
-    if meta_keywords.is_some() && meta_keywords.unwrap().contains(&String::from(s)) {
-        return;
-    } else if !file.contains("/includes/") {
-        add_to_meta_keywords(file, s, dryrun)
-    }
+// Give up on a repo that can't be read, like a mistyped `--repo`
+// or a versioned repo without its current version.
+fn unreadable(path: &str, error: io::Error) -> ! {
+    eprintln!("Unable to check {path}: {error}");
+    std::process::exit(1);
 }
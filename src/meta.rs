@@ -1,51 +1,93 @@
 //! Functions for working with metadata (tags, facets, keywords) in our docs.
 
-use std::collections::BTreeSet;
-use std::fs::read_to_string;
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
 
 use regex::Regex;
 
+use crate::config::{Config, LangSource};
 use crate::files::read_lines;
-use crate::types::{JavaSyncness, Language, Reason};
-use crate::CODE_TABS_STRINGS_2;
+use crate::rst::{self, Directive};
+use crate::types::{CodeExampleKind, Language, Reason, Variant};
+use crate::vfs::Vfs;
 
-// Returns true if the file needs a "code example" tag, and the Reason.
-pub fn check_needs_code_example_tag(path: &str, strings: Vec<String>) -> Option<Reason> {
-    if path.contains("/includes/") {
-        return None;
-    }
-
-    let lines = read_lines(path);
+// Directives that have code in them.
+const CODE_DIRECTIVES: &[&str] = &[
+    "code",
+    "code-block",
+    "io-code-block",
+    "literalinclude",
+    "sourcecode",
+];
 
-    for line in &lines {
-        for item in &strings {
-            if line.contains(item) {
-                return Some(Reason::CodeExample(item.to_string()));
-            }
-        }
-    }
+// Options that make a `literalinclude` only show part of a file.
+const PARTIAL_OPTIONS: &[&str] = &[
+    "lines",
+    "start-after",
+    "end-before",
+    "start-at",
+    "end-at",
+    "pyobject",
+];
 
-    None
+/// Returns a Reason if the page has any of the `directives`
+/// configured to count as a code example, for the most involved one.
+pub fn check_needs_code_example_directive(
+    directives: &[Directive],
+    config: &BTreeMap<String, bool>,
+) -> Option<Reason> {
+    let counts = |name: &str| config.get(name).copied().unwrap_or(false);
+    rst::walk(directives)
+        .into_iter()
+        .filter(|d| {
+            counts(&d.name)
+                && (d.name != "procedure"
+                    || d.walk()
+                        .iter()
+                        .any(|c| CODE_DIRECTIVES.contains(&c.name.as_str())))
+        })
+        .map(|d| Reason::CodeExample(code_example_kind(d), format!("{}::", d.name)))
+        .max()
 }
 
-pub fn check_needs_lang_metadata(path: &str) -> Option<Reason> {
-    let lines = read_lines(path);
+// Whole-file `literalinclude`s are runnable; everything else is a snippet.
+fn code_example_kind(directive: &Directive) -> CodeExampleKind {
+    if directive.name == "literalinclude"
+        && !PARTIAL_OPTIONS
+            .iter()
+            .any(|o| directive.option(o).is_some())
+    {
+        CodeExampleKind::Runnable
+    } else {
+        CodeExampleKind::Snippet
+    }
+}
 
+pub fn check_needs_lang_metadata(
+    lines: &[String],
+    directives: &[Directive],
+    sources: &BTreeSet<LangSource>,
+) -> Option<Reason> {
     let mut langs_on_page: BTreeSet<Language> = BTreeSet::new();
 
-    for line in lines.iter() {
-        if line.contains(CODE_TABS_STRINGS_2) {
-            let tabids = get_tabids(&lines);
-            for s in tabids {
-                let lang = match Language::from_str(&s) {
-                    Ok(l) => l,
-                    Err(_) => continue,
-                };
-                langs_on_page.insert(lang);
-            }
+    if sources.contains(&LangSource::Tabs) {
+        for (_, s) in get_driver_tabids(directives) {
+            let lang = match Language::from_str(&s) {
+                Ok(l) => l,
+                Err(_) => continue,
+            };
+            langs_on_page.insert(lang);
         }
     }
+    if sources.contains(&LangSource::CodeBlock) {
+        langs_on_page.extend(get_code_block_languages(lines));
+    }
+    if sources.contains(&LangSource::LanguageOption) {
+        langs_on_page.extend(get_language_option_languages(lines));
+    }
+    if sources.contains(&LangSource::Literalinclude) {
+        langs_on_page.extend(get_literalinclude_languages(lines));
+    }
 
     if langs_on_page.is_empty() {
         None
@@ -54,88 +96,124 @@ pub fn check_needs_lang_metadata(path: &str) -> Option<Reason> {
     }
 }
 
-pub fn check_needs_nodejs_tag(path: &str) -> Option<Reason> {
-    let lines = read_lines(path);
-    let tabids: Vec<String> = get_tabids(&lines);
-    if tabids.contains(&String::from("nodejs")) {
-        Some(Reason::NodejsTab)
-    } else {
-        None
-    }
+// Languages named by `.. code-block:: <lang>` (or `code`/`sourcecode`).
+pub fn get_code_block_languages(lines: &[String]) -> BTreeSet<Language> {
+    let re = Regex::new(r"^\s*\.\.\s+(code-block|code|sourcecode)::\s*(\S+)").unwrap();
+    lines
+        .iter()
+        .filter_map(|line| re.captures(line))
+        .filter_map(|caps| Language::from_lexer(&caps[2]))
+        .collect()
+}
+
+// Languages named by `:language:` options.
+pub fn get_language_option_languages(lines: &[String]) -> BTreeSet<Language> {
+    let re = Regex::new(r"^\s*:language:\s*(\S+)").unwrap();
+    lines
+        .iter()
+        .filter_map(|line| re.captures(line))
+        .filter_map(|caps| Language::from_lexer(&caps[1]))
+        .collect()
 }
 
-pub fn check_needs_java_tag(path: &str) -> Option<Reason> {
-    let sync = String::from("java-sync");
-    let _async = String::from("java-async");
+// Languages of files pulled in by `literalinclude` or an `io-code-block`'s `input`.
+pub fn get_literalinclude_languages(lines: &[String]) -> BTreeSet<Language> {
+    let re = Regex::new(r"^\s*\.\.\s+(literalinclude|input)::\s*(\S+)").unwrap();
+    lines
+        .iter()
+        .filter_map(|line| re.captures(line))
+        .filter_map(|caps| {
+            let ext = caps[2].rsplit_once('.')?.1.to_string();
+            Language::from_extension(&ext)
+        })
+        .collect()
+}
 
-    let lines = read_lines(path);
-    let tabids: Vec<String> = get_tabids(&lines);
+/// Run every detector that only needs the contents of the file itself.
+pub fn content_reasons(lines: &[String], config: &Config) -> BTreeSet<Reason> {
+    let directives = rst::parse(lines);
+    [
+        check_needs_code_example_directive(&directives, &config.code_example_directives),
+        check_needs_lang_metadata(lines, &directives, &config.lang_sources),
+        check_needs_nodejs_tag(&directives),
+        check_needs_variant_tags(&directives),
+        check_needs_compass_tag(&directives),
+        check_needs_atlas_api_tag(&directives),
+        check_needs_atlas_cli_tag(&directives),
+        check_needs_atlas_ui_tag(&directives),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
 
-    match (tabids.contains(&sync), tabids.contains(&_async)) {
-        (true, true) => return Some(Reason::Java(JavaSyncness::Both)),
-        (true, false) => return Some(Reason::Java(JavaSyncness::Sync)),
-        (false, true) => return Some(Reason::Java(JavaSyncness::Async)),
-        (false, false) => return None,
+/// The keyword reason a tab with `tabid` gives, if any.
+pub fn tab_reason(tabid: &str) -> Option<Reason> {
+    match tabid {
+        "nodejs" => Some(Reason::NodejsTab),
+        "compass" => Some(Reason::CompassTab),
+        "atlasapi" | "api" | "atlas-api" => Some(Reason::AtlasApiTab),
+        "atlascli" | "cli" | "atlas-cli" => Some(Reason::AtlasCliTab),
+        "atlasui" | "ui" | "atlas-ui" => Some(Reason::AtlasUiTab),
+        _ => None,
     }
 }
 
-pub fn check_needs_compass_tag(path: &str) -> Option<Reason> {
-    let lines = read_lines(path);
-    let tabids: Vec<String> = get_tabids(&lines);
-    if tabids.contains(&String::from("compass")) {
-        Some(Reason::CompassTab)
-    } else {
-        None
-    }
+// `reason`, if any tab gives it. Unlike languages, these count in any set
+// of tabs, since Atlas and Compass tabs are usually in plain `tabs`.
+fn check_tab_reason(directives: &[Directive], reason: Reason) -> Option<Reason> {
+    rst::walk(directives)
+        .iter()
+        .filter(|d| d.name == "tab")
+        .filter_map(|d| d.option("tabid"))
+        .any(|tabid| tab_reason(tabid).as_ref() == Some(&reason))
+        .then_some(reason)
 }
 
-pub fn check_needs_atlas_api_tag(path: &str) -> Option<Reason> {
-    let lines = read_lines(path);
-    let tabids: Vec<String> = get_tabids(&lines);
-    if tabids.contains(&String::from("atlasapi"))
-        || tabids.contains(&String::from("api"))
-        || tabids.contains(&String::from("atlas-api"))
-    {
-        Some(Reason::AtlasApiTab)
-    } else {
-        None
-    }
+pub fn check_needs_nodejs_tag(directives: &[Directive]) -> Option<Reason> {
+    check_tab_reason(directives, Reason::NodejsTab)
 }
 
-pub fn check_needs_atlas_cli_tag(path: &str) -> Option<Reason> {
-    let lines = read_lines(path);
-    let tabids: Vec<String> = get_tabids(&lines);
-    if tabids.contains(&String::from("atlascli"))
-        || tabids.contains(&String::from("cli"))
-        || tabids.contains(&String::from("atlas-cli"))
-    {
-        Some(Reason::AtlasCliTab)
-    } else {
+/// Tabs for a particular flavor of a driver, like `java-sync` or `motor`,
+/// each need that flavor's keyword.
+pub fn check_needs_variant_tags(directives: &[Directive]) -> Option<Reason> {
+    let ids: BTreeSet<String> = get_driver_tabids(directives)
+        .iter()
+        .filter_map(|(_, tabid)| Variant::from_tabid(tabid))
+        .map(|v| v.id.to_string())
+        .collect();
+    if ids.is_empty() {
         None
+    } else {
+        Some(Reason::Variants(ids))
     }
 }
 
-pub fn check_needs_atlas_ui_tag(path: &str) -> Option<Reason> {
-    let lines = read_lines(path);
-    let tabids: Vec<String> = get_tabids(&lines);
-    if tabids.contains(&String::from("atlasui"))
-        || tabids.contains(&String::from("ui"))
-        || tabids.contains(&String::from("atlas-ui"))
-    {
-        Some(Reason::AtlasUiTab)
-    } else {
-        None
-    }
+pub fn check_needs_compass_tag(directives: &[Directive]) -> Option<Reason> {
+    check_tab_reason(directives, Reason::CompassTab)
+}
+
+pub fn check_needs_atlas_api_tag(directives: &[Directive]) -> Option<Reason> {
+    check_tab_reason(directives, Reason::AtlasApiTab)
+}
+
+pub fn check_needs_atlas_cli_tag(directives: &[Directive]) -> Option<Reason> {
+    check_tab_reason(directives, Reason::AtlasCliTab)
 }
 
-pub fn get_meta_keywords(path: &str) -> Option<Vec<String>> {
+pub fn check_needs_atlas_ui_tag(directives: &[Directive]) -> Option<Reason> {
+    check_tab_reason(directives, Reason::AtlasUiTab)
+}
+
+pub fn get_meta_keywords(vfs: &dyn Vfs, path: &str) -> Option<Vec<String>> {
+    meta_keywords_from_lines(&read_lines(vfs, path))
+}
+
+pub fn meta_keywords_from_lines(lines: &[String]) -> Option<Vec<String>> {
     let mut keywords: Vec<String> = vec![];
-    let lines = read_lines(path);
     for line in lines.iter() {
-        if line.contains(":keywords:") {
-            let s = line.clone();
-            let s = s.trim_start();
-            let s = s.trim_start_matches(":keywords:");
+        // Only the option itself, like `replace_meta_keywords` edits.
+        if let Some(s) = line.trim_start().strip_prefix(":keywords:") {
             let s = s.trim_start();
             for item in s.split(',').map(|s| s.trim()) {
                 if !item.is_empty() {
@@ -148,12 +226,15 @@ pub fn get_meta_keywords(path: &str) -> Option<Vec<String>> {
     None
 }
 
-pub fn get_pl_facet_values(path: &str) -> Option<BTreeSet<Language>> {
-    let contents = read_to_string(path).expect("Oops opening file");
+pub fn get_pl_facet_values(vfs: &dyn Vfs, path: &str) -> Option<BTreeSet<Language>> {
+    let contents = vfs.read(path).expect("Oops opening file");
+    pl_facet_values_from_str(&contents)
+}
 
+pub fn pl_facet_values_from_str(contents: &str) -> Option<BTreeSet<Language>> {
     let re = Regex::new(r"\.\. facet::(.*)\n(.*):name: programming_language\n.(.*):values:(.*)")
         .unwrap();
-    let r = re.find(&contents);
+    let r = re.find(contents);
 
     #[allow(clippy::question_mark)]
     if r.is_none() {
@@ -180,13 +261,140 @@ pub fn get_pl_facet_values(path: &str) -> Option<BTreeSet<Language>> {
     Some(langs)
 }
 
-pub fn get_tabids(lines: &[String]) -> Vec<String> {
-    let mut tabids: Vec<String> = vec![];
-    for line in lines.iter() {
-        if line.contains(":tabid:") {
-            tabids.push(line.split(":tabid:").map(|s| s.trim()).collect::<Vec<_>>()[1].to_string());
-        }
+/// The values of every `.. facet::` in `directives`, by facet name.
+pub fn get_facets(directives: &[Directive]) -> BTreeMap<String, BTreeSet<String>> {
+    let mut facets: BTreeMap<String, BTreeSet<String>> = BTreeMap::default();
+    for facet in rst::walk(directives)
+        .into_iter()
+        .filter(|d| d.name == "facet")
+    {
+        let (Some(name), Some(values)) = (facet.option("name"), facet.option("values")) else {
+            continue;
+        };
+        facets.entry(name.to_string()).or_default().extend(
+            values
+                .split(',')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .map(String::from),
+        );
     }
+    facets
+}
+
+/// Whether `directive` is a set of driver tabs: `tabs-drivers`,
+/// or `tabs` that go with a `tabs-selector:: drivers`.
+pub fn is_driver_tabset(directive: &Directive) -> bool {
+    directive.name == "tabs-drivers"
+        || (directive.name == "tabs" && directive.option("tabset") == Some("drivers"))
+}
+
+/// The tabids (and their 0-based lines) of tabs that are direct children
+/// of a set of driver tabs. Tabs in other sets, including sets nested
+/// inside a driver tab, don't count.
+pub fn get_driver_tabids(directives: &[Directive]) -> Vec<(usize, String)> {
+    rst::walk(directives)
+        .into_iter()
+        .filter(|d| is_driver_tabset(d))
+        .flat_map(|tabset| &tabset.children)
+        .filter(|tab| tab.name == "tab")
+        .filter_map(|tab| Some((tab.line, tab.option("tabid")?.to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn atlas_tabs_count_outside_driver_tabs() {
+        let lines = lines(
+            "\
+.. tabs::
+
+   .. tab::
+      :tabid: atlas-ui
+
+      Click **Create**.
 
-    tabids
+   .. tab::
+      :tabid: python
+
+      Run it.
+",
+        );
+        let reasons = content_reasons(&lines, &Config::default());
+        assert!(reasons.contains(&Reason::AtlasUiTab));
+        assert!(!reasons.iter().any(|r| matches!(r, Reason::Languages(_))));
+    }
+
+    #[test]
+    fn code_block_languages() {
+        let lines = lines(
+            "\
+.. code-block:: golang
+
+.. code:: Python
+
+   .. sourcecode:: js
+
+.. code-block:: none
+
+.. code-block:: cobol
+",
+        );
+        assert_eq!(
+            get_code_block_languages(&lines),
+            BTreeSet::from([Language::Go, Language::Javascript, Language::Python])
+        );
+    }
+
+    #[test]
+    fn language_option_languages() {
+        let lines = lines(
+            "\
+.. io-code-block::
+
+   .. input::
+      :language: python
+
+   .. output::
+      :language: json
+
+.. literalinclude:: /code/example.txt
+   :language: cobol
+",
+        );
+        assert_eq!(
+            get_language_option_languages(&lines),
+            BTreeSet::from([Language::Python])
+        );
+    }
+
+    #[test]
+    fn literalinclude_languages() {
+        let lines = lines(
+            "\
+.. literalinclude:: /code/example.go
+
+.. io-code-block::
+
+   .. input:: ../code/example.JS
+
+.. literalinclude:: /code/example.cob
+
+.. literalinclude:: /code/Makefile
+
+.. literalinclude:: ../code.v2/README
+",
+        );
+        assert_eq!(
+            get_literalinclude_languages(&lines),
+            BTreeSet::from([Language::Go, Language::Javascript])
+        );
+    }
 }
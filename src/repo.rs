@@ -0,0 +1,411 @@
+//! The tagging workflow, for tools that want it without the CLI.
+//!
+//! ```no_run
+//! use codetagger::repo::Repo;
+//!
+//! let repo = Repo::open("../docs-node").expect("Unable to open repo");
+//! let mut cache = repo.cache(false);
+//! let scan = repo.scan(&mut cache).expect("Unable to read repo");
+//! for (path, edit) in &scan.edits {
+//!     println!("{path}: {edit}");
+//! }
+//! scan.apply(repo.vfs(), true);
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Display;
+use std::io;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::cache::{Cache, CodeExampleStrings, CACHE_DIR};
+use crate::config::Config;
+use crate::diagnostics::ExpectedTags;
+use crate::files::{add_pl_facet, is_kind_keyword, is_page, read_lines, tag_with_keyword};
+use crate::meta::{
+    get_facets, get_pl_facet_values, meta_keywords_from_lines, pl_facet_values_from_str,
+};
+use crate::rst::{self, Directive};
+use crate::suppressions::Suppressions;
+use crate::types::{Language, Reason};
+use crate::versions::current_root;
+use crate::vfs::{walk, DiskFs, Vfs};
+
+/// A docs repo and the settings to check it with.
+#[derive(Debug, Clone)]
+pub struct Repo {
+    /// The top-level repo, where `.codetagger` (settings, suppressions,
+    /// keywords and the cache) lives.
+    pub path: String,
+    /// Where pages are scanned from. For versioned repos,
+    /// the current version's directory.
+    pub root: String,
+    pub config: Config,
+    /// Where the repo's files are read from.
+    pub vfs: Arc<dyn Vfs>,
+}
+
+impl Repo {
+    /// Open the repo at `path`, with settings from its
+    /// `.codetagger/config.toml` and `snooty.toml`.
+    /// Fails if it's versioned and the current version isn't there.
+    pub fn open(path: &str) -> io::Result<Repo> {
+        Repo::with_config(path, Config::for_repo(&DiskFs, path))
+    }
+
+    pub fn with_config(path: &str, config: Config) -> io::Result<Repo> {
+        Repo::with_vfs(path, config, Arc::new(DiskFs))
+    }
+
+    /// Like `with_config`, but reading files through `vfs`.
+    pub fn with_vfs(path: &str, config: Config, vfs: Arc<dyn Vfs>) -> io::Result<Repo> {
+        Ok(Repo {
+            path: path.to_string(),
+            root: current_root(&*vfs, path, config.current_version.as_deref())?,
+            config,
+            vfs,
+        })
+    }
+
+    pub fn vfs(&self) -> &dyn Vfs {
+        &*self.vfs
+    }
+
+    /// The scan cache for this repo, or an empty one with `no_cache`.
+    pub fn cache(&self, no_cache: bool) -> Cache {
+        let cache = if no_cache {
+            Cache::in_memory(&self.config)
+        } else {
+            Cache::load(&self.path, &self.config)
+        };
+        cache.with_vfs(self.vfs.clone())
+    }
+
+    pub fn suppressions(&self) -> Suppressions {
+        Suppressions::load(self.vfs(), &self.path)
+    }
+
+    /// Read the page (or include) at `path`.
+    pub fn page(&self, path: &str) -> Page {
+        Page::load(self.vfs(), path)
+    }
+
+    /// Scan (or look up) every file, and work out what each page needs.
+    /// Fails if the repo can't be read.
+    pub fn scan(&self, cache: &mut Cache) -> io::Result<Scan> {
+        let suppressions = self.suppressions();
+        let files = cache.scan(&self.root)?;
+        let strings = cache.code_example_strings();
+
+        let mut reasons: HashSet<FileAndReason> = HashSet::default();
+        for path in &files {
+            for reason in cache.own_reasons(path, &strings) {
+                reasons.insert(FileAndReason(path.clone(), Some(reason)));
+            }
+        }
+
+        // What each page should have: its own tags, plus
+        // the languages of everything it (transitively) includes.
+        let mut pages: Vec<Page> = vec![];
+        let mut expected: Vec<(String, ExpectedTags)> = vec![];
+        for path in files.iter().filter(|p| is_page(p)) {
+            let mut tags = cache.expected_tags(path, &strings);
+            suppressions.filter(path, &mut tags);
+            pages.push(self.page(path));
+            expected.push((path.clone(), tags));
+        }
+
+        // Only what would actually change the page.
+        let mut edits: Vec<(String, Edit)> = vec![];
+        for (page, (path, tags)) in pages.iter().zip(&expected) {
+            if !tags.languages.is_empty() && page.languages.as_ref() != Some(&tags.languages) {
+                edits.push((path.clone(), Edit::SetLanguages(tags.languages.clone())));
+            }
+        }
+        for (page, (path, tags)) in pages.iter().zip(&expected) {
+            for keyword in tags.keywords.iter().filter(|k| page.needs_keyword(k)) {
+                edits.push((path.clone(), Edit::AddKeyword(keyword.clone())));
+            }
+        }
+
+        Ok(Scan {
+            files,
+            strings,
+            reasons,
+            pages,
+            expected,
+            edits,
+        })
+    }
+
+    /// Panic if any file has more than one programming language facet.
+    pub fn check_single_pl_facet(&self) {
+        for filepath in walk(self.vfs(), &self.root, &[".git", CACHE_DIR]).unwrap_or_default() {
+            let lines = read_lines(self.vfs(), &filepath);
+            let mut count = 0;
+            for line in lines {
+                if line.contains("programming_language") {
+                    count += 1
+                }
+                if count == 2 {
+                    panic!("too many PL lines: {filepath}")
+                }
+            }
+        }
+    }
+}
+
+/// A page (or include) and the tags it has.
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub path: String,
+    pub directives: Vec<Directive>,
+    pub keywords: Option<Vec<String>>,
+    /// Values of every facet on the page, by facet name.
+    pub facets: BTreeMap<String, BTreeSet<String>>,
+    /// The programming_language facet's values, if it has one.
+    pub languages: Option<BTreeSet<Language>>,
+}
+
+impl Page {
+    pub fn load(vfs: &dyn Vfs, path: &str) -> Page {
+        let contents = vfs.read(path).unwrap_or_default();
+        let lines: Vec<String> = contents.lines().map(String::from).collect();
+        let directives = rst::parse(&lines);
+        Page {
+            path: path.to_string(),
+            keywords: meta_keywords_from_lines(&lines),
+            facets: get_facets(&directives),
+            languages: pl_facet_values_from_str(&contents),
+            directives,
+        }
+    }
+
+    pub fn is_include(&self) -> bool {
+        self.path.contains("/includes/")
+    }
+
+    /// Whether adding `keyword` would change the page. A code example kind
+    /// keyword also replaces any other kind's.
+    pub fn needs_keyword(&self, keyword: &str) -> bool {
+        let keywords = self.keywords.as_deref().unwrap_or_default();
+        !keywords.iter().any(|k| k == keyword)
+            || (is_kind_keyword(keyword)
+                && keywords.iter().any(|k| k != keyword && is_kind_keyword(k)))
+    }
+}
+
+/// A change to one page's tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    AddKeyword(String),
+    /// Add these to the programming_language facet, keeping what's there.
+    AddLanguages(BTreeSet<Language>),
+    /// Replace the programming_language facet with one for these.
+    SetLanguages(BTreeSet<Language>),
+}
+
+impl Edit {
+    /// Make the edit to `path` (or with `dryrun`, just report it).
+    pub fn apply(&self, vfs: &dyn Vfs, path: &str, dryrun: bool) {
+        match self {
+            Edit::AddKeyword(keyword) => tag_with_keyword(vfs, path, keyword, dryrun),
+            Edit::AddLanguages(langs) => {
+                let mut all = get_pl_facet_values(vfs, path).unwrap_or_default();
+                all.extend(langs.iter().cloned());
+                add_pl_facet(vfs, path, dryrun, all);
+            }
+            Edit::SetLanguages(langs) => add_pl_facet(vfs, path, dryrun, langs.clone()),
+        }
+    }
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edit::AddKeyword(k) => write!(f, "add keyword `{k}`"),
+            Edit::AddLanguages(langs) => write!(
+                f,
+                "add `{}` to programming_language facet",
+                langs.iter().format(", ")
+            ),
+            Edit::SetLanguages(langs) => write!(
+                f,
+                "set programming_language facet to `{}`",
+                langs.iter().format(", ")
+            ),
+        }
+    }
+}
+
+/// A pair of a file path and optional Reason for needed tagging.
+/// This is hashable so that we can have multiple entries per file.
+#[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash)]
+pub struct FileAndReason(pub String, pub Option<Reason>);
+
+/// What a scan of a repo found.
+#[derive(Debug, Default)]
+pub struct Scan {
+    /// Every file scanned.
+    pub files: Vec<String>,
+    /// Strings that make a page including them need a code example keyword.
+    pub strings: CodeExampleStrings,
+    /// Why each file needs tagging, from the file alone.
+    pub reasons: HashSet<FileAndReason>,
+    /// Every page, with the tags it had when scanned.
+    pub pages: Vec<Page>,
+    /// The tags each page should have, minus anything suppressed.
+    pub expected: Vec<(String, ExpectedTags)>,
+    /// The edits that give each page its expected tags.
+    pub edits: Vec<(String, Edit)>,
+}
+
+impl Scan {
+    /// Make the planned edits (or with `dryrun`, just report them).
+    pub fn apply(&self, vfs: &dyn Vfs, dryrun: bool) {
+        for (path, edit) in &self.edits {
+            edit.apply(vfs, path, dryrun);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::MemoryFs;
+
+    const PAGE: &str = "\
+=====
+Index
+=====
+
+.. tabs-drivers::
+
+   .. tab::
+      :tabid: python
+
+      .. code-block:: python
+
+         print(1)
+";
+
+    const GO_PAGE: &str = "\
+==
+Go
+==
+
+.. include:: /includes/go.rst
+";
+
+    const GO_INCLUDE: &str = "\
+.. code-block:: go
+
+   fmt.Println(1)
+";
+
+    fn memory_repo() -> (Arc<MemoryFs>, Repo) {
+        let vfs = Arc::new(MemoryFs::new(
+            [
+                ("/docs/source/index.txt", PAGE),
+                ("/docs/source/go.txt", GO_PAGE),
+                ("/docs/source/includes/go.rst", GO_INCLUDE),
+            ]
+            .map(|(path, contents)| (path.to_string(), contents.to_string())),
+        ));
+        let repo = Repo::with_vfs("/docs", Config::for_repo(&*vfs, "/docs"), vfs.clone()).unwrap();
+        (vfs, repo)
+    }
+
+    #[test]
+    fn scan_plans_edits() {
+        let (_, repo) = memory_repo();
+        let scan = repo.scan(&mut repo.cache(true)).unwrap();
+        let go = BTreeSet::from([Language::Go]);
+        assert!(scan
+            .edits
+            .contains(&("/docs/source/go.txt".to_string(), Edit::SetLanguages(go))));
+        assert!(!scan
+            .edits
+            .iter()
+            .any(|(path, _)| path.contains("/includes/")));
+    }
+
+    #[test]
+    fn apply_edits_to_memory_fs() {
+        let (vfs, repo) = memory_repo();
+        let before = vfs.files();
+        repo.scan(&mut repo.cache(true))
+            .unwrap()
+            .apply(repo.vfs(), false);
+        let files = vfs.files();
+
+        let index = &files["/docs/source/index.txt"];
+        assert!(index.contains(".. facet::\n   :name: programming_language\n   :values: python\n"));
+        assert!(index.contains(":keywords: code example"));
+        assert!(files["/docs/source/go.txt"].contains(":values: go\n"));
+        assert_eq!(
+            files["/docs/source/includes/go.rst"],
+            before["/docs/source/includes/go.rst"]
+        );
+    }
+
+    #[test]
+    fn versioned_repo_uses_top_level_suppressions() {
+        let vfs = Arc::new(MemoryFs::new(
+            [
+                ("/docs/current/snooty.toml", "name = \"docs\"\n"),
+                ("/docs/current/source/go.txt", GO_PAGE),
+                ("/docs/current/source/includes/go.rst", GO_INCLUDE),
+                (
+                    "/docs/.codetagger/suppressions",
+                    "current/source/go.txt\tlanguage:go\n",
+                ),
+            ]
+            .map(|(path, contents)| (path.to_string(), contents.to_string())),
+        ));
+        let repo = Repo::with_vfs("/docs", Config::for_repo(&*vfs, "/docs"), vfs).unwrap();
+        assert_eq!(repo.root, "/docs/current");
+        let scan = repo.scan(&mut repo.cache(true)).unwrap();
+        assert!(!scan
+            .edits
+            .iter()
+            .any(|(_, edit)| matches!(edit, Edit::SetLanguages(_))));
+    }
+
+    #[test]
+    fn second_scan_plans_no_edits() {
+        let (_, repo) = memory_repo();
+        repo.scan(&mut repo.cache(true))
+            .unwrap()
+            .apply(repo.vfs(), false);
+        let scan = repo.scan(&mut repo.cache(true)).unwrap();
+        assert_eq!(scan.edits, []);
+        let go = scan
+            .pages
+            .iter()
+            .find(|p| p.path == "/docs/source/go.txt")
+            .unwrap();
+        assert_eq!(go.languages, Some(BTreeSet::from([Language::Go])));
+    }
+
+    #[test]
+    fn missing_current_version_is_an_error() {
+        let vfs = Arc::new(MemoryFs::new([(
+            String::from("/docs/v6.0/snooty.toml"),
+            String::from("name = \"docs\"\n"),
+        )]));
+        let error = Repo::with_vfs("/docs", Config::for_repo(&*vfs, "/docs"), vfs).unwrap_err();
+        assert!(error.to_string().contains("no `current` version"));
+    }
+
+    #[test]
+    fn dryrun_leaves_memory_fs_alone() {
+        let (vfs, repo) = memory_repo();
+        let before = vfs.files();
+        repo.scan(&mut repo.cache(true))
+            .unwrap()
+            .apply(repo.vfs(), true);
+        assert_eq!(vfs.files(), before);
+    }
+}
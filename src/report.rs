@@ -0,0 +1,241 @@
+//! A static HTML report of how much of the repo is tagged.
+//!
+//! Everything, including styles, is in the one file, so it can be
+//! emailed around or opened straight from disk.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use itertools::Itertools;
+
+use crate::cache::Cache;
+use crate::diagnostics::{diagnose, Diagnostic, DiagnosticKind, ExpectedTags};
+use crate::files::is_page;
+use crate::review::evidence;
+use crate::suppressions::{unused_suppressions, Suppressions};
+use crate::types::Reason;
+
+const CODE_EXAMPLE: &str = "code example";
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+th { background: #f4f4f4; }
+.ok { color: #1a7f37; }
+.missing { color: #cf222e; }
+.na { color: #888; }
+summary { cursor: pointer; font-weight: bold; margin: 0.5em 0; }
+ul { margin: 0; padding-left: 1.2em; }
+";
+
+/// What we know about one page for the report.
+struct PageReport {
+    path: String,
+    reasons: Vec<Reason>,
+    expected: ExpectedTags,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl PageReport {
+    fn needs_facet(&self) -> bool {
+        !self.expected.languages.is_empty()
+    }
+
+    fn has_facet(&self) -> bool {
+        !self
+            .diagnostics
+            .iter()
+            .any(|d| matches!(d.kind, DiagnosticKind::MissingLanguages(_)))
+    }
+
+    fn needs_code_example(&self) -> bool {
+        self.expected.keywords.contains(CODE_EXAMPLE)
+    }
+
+    fn has_code_example(&self) -> bool {
+        !self
+            .diagnostics
+            .iter()
+            .any(|d| d.kind == DiagnosticKind::MissingKeyword(CODE_EXAMPLE.to_string()))
+    }
+}
+
+/// Counts for one directory.
+#[derive(Default)]
+struct Coverage {
+    pages: usize,
+    need_facet: usize,
+    have_facet: usize,
+    need_code_example: usize,
+    have_code_example: usize,
+}
+
+impl Coverage {
+    fn add(&mut self, page: &PageReport) {
+        self.pages += 1;
+        if page.needs_facet() {
+            self.need_facet += 1;
+            self.have_facet += page.has_facet() as usize;
+        }
+        if page.needs_code_example() {
+            self.need_code_example += 1;
+            self.have_code_example += page.has_code_example() as usize;
+        }
+    }
+}
+
+/// Write the report for `repo` to `output`.
+pub fn write_report(repo: &str, cache: &Cache, output: &str) {
+    let suppressions = Suppressions::load(cache.vfs(), repo);
+    let strings = cache.code_example_strings();
+
+    let mut pages: Vec<PageReport> = vec![];
+    for (path, _) in cache.entries().filter(|(path, _)| is_page(path)) {
+        let mut expected = cache.expected_tags(path, &strings);
+        suppressions.filter(path, &mut expected);
+        let contents = cache.vfs().read(path).unwrap_or_default();
+        pages.push(PageReport {
+            path: path.clone(),
+            reasons: cache.page_reasons(path, &strings).into_iter().collect(),
+            diagnostics: diagnose(path, &contents, &expected),
+            expected,
+        });
+    }
+
+    let mut by_dir: BTreeMap<String, Vec<&PageReport>> = BTreeMap::default();
+    for page in &pages {
+        by_dir
+            .entry(directory(repo, &page.path))
+            .or_default()
+            .push(page);
+    }
+
+    let mut html = String::new();
+    html += "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n";
+    html += &format!("<title>Tagging coverage: {}</title>\n", escape(repo));
+    html += &format!("<style>{STYLE}</style>\n</head>\n<body>\n");
+    html += &format!("<h1>Tagging coverage: {}</h1>\n", escape(repo));
+
+    let mut total = Coverage::default();
+    pages.iter().for_each(|p| total.add(p));
+    html += "<h2>Coverage by directory</h2>\n<table>\n";
+    html += "<tr><th>Directory</th><th>Pages</th><th>programming_language facet</th><th>code example keyword</th></tr>\n";
+    for (dir, dir_pages) in &by_dir {
+        let mut coverage = Coverage::default();
+        dir_pages.iter().for_each(|p| coverage.add(p));
+        html += &coverage_row(&format!("<a href=\"#{0}\">{0}</a>", escape(dir)), &coverage);
+    }
+    html += &coverage_row("<b>Total</b>", &total);
+    html += "</table>\n";
+
+    html += "<h2>Problems</h2>\n";
+    let mut problems: Vec<String> = pages
+        .iter()
+        .flat_map(|p| &p.diagnostics)
+        .map(|d| format!("{}:{}: {}", d.path, d.line + 1, d.message()))
+        .collect();
+    for (tabid, locations) in cache.unknown_tabids() {
+        for (path, line) in locations {
+            problems.push(format!(
+                "{path}:{}: unknown driver tabid `{tabid}`",
+                line + 1
+            ));
+        }
+    }
+    for unused in unused_suppressions(cache, &suppressions) {
+        problems.push(format!("unused suppression: {unused}"));
+    }
+    if problems.is_empty() {
+        html += "<p class=\"ok\">No problems found.</p>\n";
+    } else {
+        html += "<ul>\n";
+        for problem in &problems {
+            html += &format!("<li>{}</li>\n", escape(problem));
+        }
+        html += "</ul>\n";
+    }
+
+    html += "<h2>Pages</h2>\n";
+    for (dir, dir_pages) in &by_dir {
+        html += &format!(
+            "<details id=\"{0}\">\n<summary>{0} ({1} pages)</summary>\n<table>\n",
+            escape(dir),
+            dir_pages.len()
+        );
+        html += "<tr><th>Page</th><th>Facet</th><th>Code example</th><th>Reasons</th><th>Problems</th></tr>\n";
+        for page in dir_pages {
+            html += &format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(page.path.rsplit('/').next().unwrap_or_default()),
+                status(page.needs_facet(), page.has_facet()),
+                status(page.needs_code_example(), page.has_code_example()),
+                list(
+                    page.reasons
+                        .iter()
+                        .flat_map(|r| evidence(cache, &page.path, r))
+                ),
+                list(page.diagnostics.iter().map(|d| d.message())),
+            );
+        }
+        html += "</table>\n</details>\n";
+    }
+
+    html += "</body>\n</html>\n";
+    std::fs::write(output, html).expect("Unable to write report");
+    println!("📊 Wrote report to {output}");
+}
+
+// The page's directory, relative to the repo.
+fn directory(repo: &str, path: &str) -> String {
+    let rel = Path::new(path)
+        .strip_prefix(repo)
+        .unwrap_or(Path::new(path));
+    rel.parent()
+        .map(|p| String::from(p.to_string_lossy()))
+        .unwrap_or_default()
+}
+
+fn coverage_row(label: &str, coverage: &Coverage) -> String {
+    format!(
+        "<tr><td>{label}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+        coverage.pages,
+        ratio(coverage.have_facet, coverage.need_facet),
+        ratio(coverage.have_code_example, coverage.need_code_example),
+    )
+}
+
+fn ratio(have: usize, need: usize) -> String {
+    if need == 0 {
+        return String::from("<span class=\"na\">n/a</span>");
+    }
+    let class = if have == need { "ok" } else { "missing" };
+    format!(
+        "<span class=\"{class}\">{have}/{need} ({}%)</span>",
+        have * 100 / need
+    )
+}
+
+fn status(needs: bool, has: bool) -> &'static str {
+    match (needs, has) {
+        (false, _) => "<span class=\"na\">not needed</span>",
+        (true, true) => "<span class=\"ok\">✓</span>",
+        (true, false) => "<span class=\"missing\">missing</span>",
+    }
+}
+
+fn list(items: impl Iterator<Item = String>) -> String {
+    let items = items.map(|i| format!("<li>{}</li>", escape(&i))).join("");
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!("<ul>{items}</ul>")
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,204 @@
+//! Walking through proposed edits one at a time.
+
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::str::FromStr;
+
+use ansi_term::Colour::{Cyan, White};
+
+use crate::cache::Cache;
+use crate::files::{is_page, read_lines};
+use crate::meta::{
+    get_code_block_languages, get_language_option_languages, get_literalinclude_languages,
+    tab_reason,
+};
+use crate::repo::Edit;
+use crate::suppressions::{Suppression, Suppressions};
+use crate::types::{Language, Reason, Variant};
+
+/// An edit we'd like to make to a page, and why.
+#[derive(Debug, Clone)]
+pub struct Proposal {
+    pub path: String,
+    pub edit: Edit,
+    pub evidence: Vec<String>,
+}
+
+/// Work out every edit `codetagger` would make, minus anything suppressed.
+pub fn proposals(cache: &Cache, suppressions: &Suppressions) -> Vec<Proposal> {
+    let strings = cache.code_example_strings();
+    let mut proposals: Vec<Proposal> = vec![];
+
+    for (path, entry) in cache.entries().filter(|(path, _)| is_page(path)) {
+        let reasons = cache.page_reasons(path, &strings);
+        let mut expected = cache.expected_tags(path, &strings);
+        suppressions.filter(path, &mut expected);
+
+        let keywords = entry.keywords.clone().unwrap_or_default();
+        for keyword in expected.keywords.iter().filter(|k| !keywords.contains(k)) {
+            let evidence = reasons
+                .iter()
+                .filter(|r| r.keywords().contains(&keyword.as_str()))
+                .flat_map(|r| evidence(cache, path, r))
+                .collect();
+            proposals.push(Proposal {
+                path: path.clone(),
+                edit: Edit::AddKeyword(keyword.clone()),
+                evidence,
+            });
+        }
+
+        let facet = entry.facets.clone().unwrap_or_default();
+        let missing: BTreeSet<Language> = expected.languages.difference(&facet).cloned().collect();
+        if !missing.is_empty() {
+            proposals.push(Proposal {
+                path: path.clone(),
+                evidence: evidence(cache, path, &Reason::Languages(missing.clone())),
+                edit: Edit::AddLanguages(missing),
+            });
+        }
+    }
+
+    proposals
+}
+
+/// The reason, then `file:line: text` for each line it came from.
+/// For languages, that's lines on the page or anything it includes.
+pub fn evidence(cache: &Cache, path: &str, reason: &Reason) -> Vec<String> {
+    let mut files = vec![path.to_string()];
+    if let Reason::Languages(_) = reason {
+        files.extend(cache.transitive_includes(path));
+    }
+    let mut evidence = vec![format!("page {reason}")];
+    for file in files {
+        for (i, line) in read_lines(cache.vfs(), &file).iter().enumerate() {
+            if comes_from(reason, line) {
+                evidence.push(format!("  {file}:{}: {}", i + 1, line.trim()));
+            }
+        }
+    }
+    evidence
+}
+
+// Whether `line` is one that `reason` could have come from.
+fn comes_from(reason: &Reason, line: &str) -> bool {
+    let tabid = line.trim().strip_prefix(":tabid:").map(str::trim);
+    match reason {
+        // Includes are resolved, so may not be written the same way.
+        Reason::CodeExample(_, s) => {
+            line.contains(s.as_str())
+                || line.split_once("include::").is_some_and(|(_, target)| {
+                    let target = target.trim().trim_start_matches('/');
+                    !target.is_empty() && s.ends_with(target)
+                })
+        }
+        Reason::Languages(langs) => {
+            let lines = [line.to_string()];
+            let mut found = get_code_block_languages(&lines);
+            found.extend(get_language_option_languages(&lines));
+            found.extend(get_literalinclude_languages(&lines));
+            found.extend(tabid.and_then(|t| Language::from_str(t).ok()));
+            !found.is_disjoint(langs)
+        }
+        Reason::Variants(ids) => tabid
+            .and_then(Variant::from_tabid)
+            .is_some_and(|v| ids.contains(v.id)),
+        reason => tabid.and_then(tab_reason).as_ref() == Some(reason),
+    }
+}
+
+/// Ask the user about each proposed edit.
+/// Rejections are saved so they aren't proposed again, unless `dryrun`.
+pub fn review(repo: &str, cache: &Cache, dryrun: bool) {
+    let vfs = cache.vfs();
+    let mut suppressions = Suppressions::load(vfs, repo);
+    let proposals = proposals(cache, &suppressions);
+    let total = proposals.len();
+
+    for (i, proposal) in proposals.into_iter().enumerate() {
+        println!(
+            "\n[{}/{total}] {}",
+            i + 1,
+            White.bold().paint(proposal.path.as_str())
+        );
+        println!("  {}", Cyan.paint(proposal.edit.to_string()));
+        for line in &proposal.evidence {
+            println!("    {line}");
+        }
+
+        match prompt("[a]ccept, [r]eject, [e]dit, [s]kip, [q]uit? ").as_deref() {
+            Some("a") => proposal.edit.apply(vfs, &proposal.path, dryrun),
+            Some("r") => {
+                for suppression in suppressions_for(&proposal.edit) {
+                    suppressions.add(&proposal.path, suppression);
+                }
+                if !dryrun {
+                    suppressions.save(vfs);
+                }
+            }
+            Some("e") => {
+                if let Some(edit) = edit(&proposal.edit) {
+                    edit.apply(vfs, &proposal.path, dryrun);
+                }
+            }
+            Some("q") | None => break,
+            _ => continue,
+        }
+    }
+
+    if dryrun {
+        println!(
+            "{}",
+            White.paint("\n👉 This was a dry run.\nTo update files, run with `--dryrun=false`.")
+        );
+    }
+}
+
+fn suppressions_for(edit: &Edit) -> Vec<Suppression> {
+    match edit {
+        Edit::AddKeyword(k) => vec![Suppression::Keyword(k.clone())],
+        Edit::AddLanguages(langs) | Edit::SetLanguages(langs) => {
+            langs.iter().cloned().map(Suppression::Language).collect()
+        }
+    }
+}
+
+// Let the user change what's being added.
+fn edit(edit: &Edit) -> Option<Edit> {
+    match edit {
+        Edit::AddKeyword(_) => {
+            let keyword = prompt("keyword to add (empty to skip): ")?;
+            (!keyword.is_empty()).then_some(Edit::AddKeyword(keyword))
+        }
+        Edit::AddLanguages(_) | Edit::SetLanguages(_) => {
+            let values = prompt("comma-separated languages (empty to skip): ")?;
+            let mut langs: BTreeSet<Language> = BTreeSet::default();
+            for value in values
+                .split(',')
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+            {
+                match Language::from_str(value) {
+                    Ok(lang) => {
+                        langs.insert(lang);
+                    }
+                    Err(_) => println!("Skipping unknown language `{value}`"),
+                }
+            }
+            (!langs.is_empty()).then_some(Edit::AddLanguages(langs))
+        }
+    }
+}
+
+// Returns `None` at the end of input.
+fn prompt(question: &str) -> Option<String> {
+    print!("{question}");
+    std::io::stdout()
+        .flush()
+        .expect("Unable to write to stdout");
+    let mut answer = String::new();
+    match std::io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_string()),
+    }
+}
@@ -0,0 +1,180 @@
+//! A very small reStructuredText parser that only understands directives.
+//!
+//! It's enough to tell which `tab` belongs to which set of tabs, without
+//! having to get everything else about RST right.
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+
+/// A directive, like `.. tabs-drivers::`, and the directives nested in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directive {
+    pub name: String,
+    pub argument: String,
+    pub options: BTreeMap<String, String>,
+    /// 0-based line the directive starts on.
+    pub line: usize,
+    pub children: Vec<Directive>,
+}
+
+impl Directive {
+    pub fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(|s| s.as_str())
+    }
+
+    /// This directive and everything nested in it, depth first.
+    pub fn walk(&self) -> Vec<&Directive> {
+        let mut all = vec![self];
+        for child in &self.children {
+            all.extend(child.walk());
+        }
+        all
+    }
+}
+
+/// Every directive in `directives`, at any depth.
+pub fn walk(directives: &[Directive]) -> Vec<&Directive> {
+    directives.iter().flat_map(|d| d.walk()).collect()
+}
+
+struct Open {
+    indent: usize,
+    directive: Directive,
+    /// Options come straight after the directive line, before any blank line.
+    in_options: bool,
+}
+
+pub fn parse(lines: &[String]) -> Vec<Directive> {
+    let directive_re = Regex::new(r"^(\s*)\.\.\s+([A-Za-z0-9_:-]+?)::(\s+(.*))?$").unwrap();
+    let option_re = Regex::new(r"^\s*:([A-Za-z0-9_-]+):(\s+(.*))?$").unwrap();
+
+    let mut roots: Vec<Directive> = vec![];
+    let mut stack: Vec<Open> = vec![];
+
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            if let Some(open) = stack.last_mut() {
+                open.in_options = false;
+            }
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        // Anything not indented past a directive ends its body.
+        while stack.last().is_some_and(|open| indent <= open.indent) {
+            close(&mut stack, &mut roots);
+        }
+
+        if let Some(caps) = directive_re.captures(line) {
+            stack.push(Open {
+                indent,
+                directive: Directive {
+                    name: caps[2].to_string(),
+                    argument: caps.get(4).map_or("", |m| m.as_str()).trim().to_string(),
+                    line: i,
+                    ..Default::default()
+                },
+                in_options: true,
+            });
+            continue;
+        }
+
+        if let Some(open) = stack.last_mut() {
+            match option_re.captures(line) {
+                Some(caps) if open.in_options => {
+                    let value = caps.get(3).map_or("", |m| m.as_str()).trim();
+                    open.directive
+                        .options
+                        .insert(caps[1].to_string(), value.to_string());
+                }
+                _ => open.in_options = false,
+            }
+        }
+    }
+
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+fn close(stack: &mut Vec<Open>, roots: &mut Vec<Directive>) {
+    let Some(open) = stack.pop() else {
+        return;
+    };
+    match stack.last_mut() {
+        Some(parent) => parent.directive.children.push(open.directive),
+        None => roots.push(open.directive),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn parses_nested_directives() {
+        let directives = parse(&lines(
+            "\
+.. tabs-drivers::
+
+   .. tab::
+      :tabid: python
+
+      .. code-block:: python
+
+         print(1)
+
+   .. tab::
+      :tabid: java-sync
+",
+        ));
+        assert_eq!(directives.len(), 1);
+        let tabs = &directives[0];
+        assert_eq!(tabs.name, "tabs-drivers");
+        assert_eq!(tabs.children.len(), 2);
+        assert_eq!(tabs.children[0].option("tabid"), Some("python"));
+        assert_eq!(tabs.children[0].line, 2);
+        assert_eq!(tabs.children[0].children[0].name, "code-block");
+        assert_eq!(tabs.children[0].children[0].argument, "python");
+        assert_eq!(tabs.children[1].option("tabid"), Some("java-sync"));
+        assert_eq!(walk(&directives).len(), 4);
+    }
+
+    #[test]
+    fn dedenting_ends_a_directive() {
+        let directives = parse(&lines(
+            "\
+.. tabs::
+
+   .. tab::
+      :tabid: shell
+
+.. code-block:: go
+",
+        ));
+        let names: Vec<&str> = directives.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["tabs", "code-block"]);
+        assert_eq!(directives[0].children.len(), 1);
+    }
+
+    #[test]
+    fn options_end_at_a_blank_line() {
+        let directives = parse(&lines(
+            "\
+.. literalinclude:: /code/main.py
+   :language: python
+
+   :lines: 1-5
+",
+        ));
+        assert_eq!(directives[0].argument, "/code/main.py");
+        assert_eq!(directives[0].option("language"), Some("python"));
+        assert_eq!(directives[0].option("lines"), None);
+    }
+}
@@ -0,0 +1,120 @@
+//! Settings from the docs project's `snooty.toml`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use regex::{Captures, Regex};
+use serde::Deserialize;
+
+use crate::vfs::Vfs;
+
+pub const SNOOTY_TOML: &str = "snooty.toml";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Project {
+    #[serde(default)]
+    pub name: String,
+    /// Values for `{+name+}` in the source.
+    #[serde(default)]
+    pub constants: BTreeMap<String, toml::Value>,
+}
+
+impl Project {
+    /// Read `snooty.toml` from the root of `repo`, if there is one.
+    pub fn load(vfs: &dyn Vfs, repo: &str) -> Option<Project> {
+        let path = Path::new(repo).join(SNOOTY_TOML);
+        let contents = vfs.read(&path.to_string_lossy()).ok()?;
+        match toml::from_str(&contents) {
+            Ok(project) => Some(project),
+            Err(e) => {
+                eprintln!("Ignoring {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Constants as strings, ready to substitute.
+    pub fn constant_strings(&self) -> BTreeMap<String, String> {
+        self.constants
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    toml::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// Replace `{+name+}` with the value of each known constant.
+/// Unknown constants are left alone.
+pub fn resolve_constants(contents: &str, constants: &BTreeMap<String, String>) -> String {
+    if constants.is_empty() || !contents.contains("{+") {
+        return contents.to_string();
+    }
+    let re = Regex::new(r"\{\+([A-Za-z0-9_.-]+)\+\}").unwrap();
+    re.replace_all(contents, |caps: &Captures| match constants.get(&caps[1]) {
+        Some(value) => value.clone(),
+        None => caps[0].to_string(),
+    })
+    .to_string()
+}
+
+/// Rewrite a `.. sharedinclude:: path` line so the path is in `dir`,
+/// a local checkout of the shared repo.
+pub fn resolve_sharedinclude(line: &str, dir: &str) -> String {
+    let Some((directive, path)) = line.split_once("sharedinclude::") else {
+        return line.to_string();
+    };
+    let path = Path::new(dir).join(path.trim().trim_start_matches('/'));
+    format!("{directive}sharedinclude:: {}", path.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_constants_are_resolved() {
+        let constants = BTreeMap::from([
+            (String::from("driver"), String::from("PyMongo")),
+            (String::from("version-dev"), String::from("8.0")),
+        ]);
+        assert_eq!(
+            resolve_constants("Install {+driver+} {+version-dev+} on {+os+}.", &constants),
+            "Install PyMongo 8.0 on {+os+}."
+        );
+        assert_eq!(
+            resolve_constants("Install {+driver+}.", &BTreeMap::default()),
+            "Install {+driver+}."
+        );
+    }
+
+    #[test]
+    fn constant_strings_drop_toml_quotes() {
+        let project: Project =
+            toml::from_str("name = \"docs\"\n[constants]\ndriver = \"PyMongo\"\nport = 27017\n")
+                .unwrap();
+        assert_eq!(
+            project.constant_strings(),
+            BTreeMap::from([
+                (String::from("driver"), String::from("PyMongo")),
+                (String::from("port"), String::from("27017")),
+            ])
+        );
+    }
+
+    #[test]
+    fn sharedincludes_point_into_the_shared_dir() {
+        assert_eq!(
+            resolve_sharedinclude("   .. sharedinclude:: /dbx/install.rst", "/shared"),
+            "   .. sharedinclude:: /shared/dbx/install.rst"
+        );
+        assert_eq!(
+            resolve_sharedinclude(".. include:: /includes/a.rst", "/shared"),
+            ".. include:: /includes/a.rst"
+        );
+    }
+}
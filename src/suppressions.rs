@@ -0,0 +1,330 @@
+//! Tags we've been told not to add to particular pages.
+//!
+//! Rejections from `codetagger review` are saved to `.codetagger/suppressions`
+//! in the docs repo, one per line, with a tab between the page and what's
+//! suppressed on it:
+//!
+//! ```text
+//! source/page.txt<TAB>keyword:code example
+//! source/page.txt<TAB>language:python
+//! ```
+//!
+//! Pages can also opt out themselves with an RST comment:
+//!
+//! ```text
+//! .. codetagger: ignore
+//! .. codetagger: ignore atlas-ui-tab, keyword:code example
+//! ```
+//!
+//! A bare `ignore` turns off every detector for the page (and for pages
+//! that include it, if it's an include). Otherwise, each comma-separated
+//! target is either a detector name from `DETECTOR_IDS` or a
+//! `keyword:`/`language:` tag like in the suppressions file.
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cache::{Cache, CACHE_DIR};
+use crate::diagnostics::ExpectedTags;
+use crate::types::{Language, Reason, DETECTOR_IDS};
+use crate::vfs::Vfs;
+
+const SUPPRESSIONS_FILE: &str = "suppressions";
+
+pub const IGNORE_DIRECTIVE: &str = ".. codetagger:";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Suppression {
+    Keyword(String),
+    Language(Language),
+}
+
+impl FromStr for Suppression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some(("keyword", k)) => Ok(Suppression::Keyword(k.trim().to_string())),
+            Some(("language", l)) => Language::from_str(l.trim())
+                .map(Suppression::Language)
+                .map_err(|_| format!("unknown language `{l}`")),
+            _ => Err(format!("unknown suppression `{s}`")),
+        }
+    }
+}
+
+impl Display for Suppression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Suppression::Keyword(k) => write!(f, "keyword:{k}"),
+            Suppression::Language(l) => write!(f, "language:{l}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    repo: String,
+    /// Pairs of page path (relative to the repo) and what's suppressed there.
+    entries: BTreeSet<(String, Suppression)>,
+}
+
+impl Suppressions {
+    pub fn load(vfs: &dyn Vfs, repo: &str) -> Suppressions {
+        let mut suppressions = Suppressions {
+            repo: repo.to_string(),
+            ..Default::default()
+        };
+        let contents = vfs.read(&suppressions_path(repo)).unwrap_or_default();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((page, suppression)) = line.split_once('\t') else {
+                eprintln!("Ignoring malformed suppression: {line}");
+                continue;
+            };
+            match Suppression::from_str(suppression) {
+                Ok(s) => {
+                    suppressions.entries.insert((page.to_string(), s));
+                }
+                Err(e) => eprintln!("Ignoring suppression: {e}"),
+            }
+        }
+        suppressions
+    }
+
+    pub fn save(&self, vfs: &dyn Vfs) {
+        let path = suppressions_path(&self.repo);
+        let mut contents = String::new();
+        for (page, suppression) in &self.entries {
+            contents += &format!("{page}\t{suppression}\n");
+        }
+        vfs.write(&path, &contents)
+            .expect("Unable to write suppressions");
+    }
+
+    pub fn add(&mut self, path: &str, suppression: Suppression) {
+        self.entries.insert((self.key(path), suppression));
+    }
+
+    pub fn contains(&self, path: &str, suppression: &Suppression) -> bool {
+        self.entries
+            .contains(&(self.key(path), suppression.clone()))
+    }
+
+    /// Remove anything suppressed on `path` from `expected`.
+    pub fn filter(&self, path: &str, expected: &mut ExpectedTags) {
+        expected
+            .keywords
+            .retain(|k| !self.contains(path, &Suppression::Keyword(k.clone())));
+        expected
+            .languages
+            .retain(|l| !self.contains(path, &Suppression::Language(l.clone())));
+    }
+
+    // Suppressions are stored relative to the repo, so they survive
+    // the repo being cloned somewhere else.
+    fn key(&self, path: &str) -> String {
+        Path::new(path)
+            .strip_prefix(&self.repo)
+            .map(|p| String::from(p.to_string_lossy()))
+            .unwrap_or_else(|_| path.to_string())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum IgnoreTarget {
+    Everything,
+    Detector(String),
+    Tag(Suppression),
+}
+
+/// A `.. codetagger: ignore` comment in a page.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct IgnoreDirective {
+    /// 0-based line of the comment.
+    pub line: usize,
+    pub target: IgnoreTarget,
+}
+
+pub fn parse_ignore_directives(lines: &[String]) -> Vec<IgnoreDirective> {
+    let mut directives: Vec<IgnoreDirective> = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        let Some(rest) = line.trim().strip_prefix(IGNORE_DIRECTIVE) else {
+            continue;
+        };
+        let Some(targets) = rest.trim().strip_prefix("ignore") else {
+            eprintln!(
+                "Unknown codetagger comment on line {}: {}",
+                i + 1,
+                line.trim()
+            );
+            continue;
+        };
+        let targets = targets.trim();
+        if targets.is_empty() {
+            directives.push(IgnoreDirective {
+                line: i,
+                target: IgnoreTarget::Everything,
+            });
+            continue;
+        }
+        for target in targets
+            .split(',')
+            .map(|t| t.trim())
+            .filter(|t| !t.is_empty())
+        {
+            let target = match Suppression::from_str(target) {
+                Ok(s) => IgnoreTarget::Tag(s),
+                Err(_) => IgnoreTarget::Detector(target.to_string()),
+            };
+            directives.push(IgnoreDirective { line: i, target });
+        }
+    }
+    directives
+}
+
+/// Whether `directives` turn off the detector that found `reason`.
+pub fn ignores_reason(directives: &[IgnoreDirective], reason: &Reason) -> bool {
+    directives.iter().any(|d| match &d.target {
+        IgnoreTarget::Everything => true,
+        IgnoreTarget::Detector(id) => id == reason.id(),
+        IgnoreTarget::Tag(_) => false,
+    })
+}
+
+/// Whether `directives` say not to add the tag `suppression`.
+pub fn ignores_tag(directives: &[IgnoreDirective], suppression: &Suppression) -> bool {
+    directives.iter().any(|d| match &d.target {
+        IgnoreTarget::Everything => true,
+        IgnoreTarget::Detector(_) => false,
+        IgnoreTarget::Tag(s) => s == suppression,
+    })
+}
+
+/// Remove any tags `directives` say not to add from `expected`.
+pub fn filter_ignored_tags(directives: &[IgnoreDirective], expected: &mut ExpectedTags) {
+    expected
+        .keywords
+        .retain(|k| !ignores_tag(directives, &Suppression::Keyword(k.clone())));
+    expected
+        .languages
+        .retain(|l| !ignores_tag(directives, &Suppression::Language(l.clone())));
+}
+
+/// Describe every suppression, in a page or in the suppressions file,
+/// that didn't suppress anything.
+pub fn unused_suppressions(cache: &Cache, suppressions: &Suppressions) -> Vec<String> {
+    let strings = cache.code_example_strings();
+    let mut unused: Vec<String> = vec![];
+
+    for (path, entry) in cache.entries() {
+        if entry.ignores.is_empty() {
+            continue;
+        }
+        let reasons = cache.unfiltered_page_reasons(path, &strings);
+        let expected = ExpectedTags::from_reasons(&reasons);
+        for directive in &entry.ignores {
+            let used = match &directive.target {
+                IgnoreTarget::Everything => !reasons.is_empty(),
+                IgnoreTarget::Detector(id) => reasons.iter().any(|r| r.id() == id),
+                IgnoreTarget::Tag(s) => expected_contains(&expected, s),
+            };
+            if used {
+                continue;
+            }
+            let note = match &directive.target {
+                IgnoreTarget::Detector(id) if !DETECTOR_IDS.contains(&id.as_str()) => {
+                    format!("unknown detector `{id}`")
+                }
+                IgnoreTarget::Everything => String::from("nothing to ignore"),
+                IgnoreTarget::Detector(id) => format!("`{id}` found nothing"),
+                IgnoreTarget::Tag(s) => format!("`{s}` wouldn't be added"),
+            };
+            unused.push(format!("{path}:{}: {note}", directive.line + 1));
+        }
+    }
+
+    for (page, suppression) in &suppressions.entries {
+        let path = String::from(Path::new(&suppressions.repo).join(page).to_string_lossy());
+        let reasons = cache.unfiltered_page_reasons(&path, &strings);
+        if !expected_contains(&ExpectedTags::from_reasons(&reasons), suppression) {
+            unused.push(format!(
+                "{}: `{page}\t{suppression}` wouldn't be added",
+                suppressions_path(&suppressions.repo)
+            ));
+        }
+    }
+
+    unused
+}
+
+fn expected_contains(expected: &ExpectedTags, suppression: &Suppression) -> bool {
+    match suppression {
+        Suppression::Keyword(k) => expected.keywords.contains(k),
+        Suppression::Language(l) => expected.languages.contains(l),
+    }
+}
+
+fn suppressions_path(repo: &str) -> String {
+    String::from(
+        Path::new(repo)
+            .join(CACHE_DIR)
+            .join(SUPPRESSIONS_FILE)
+            .to_string_lossy(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(s: &str) -> Vec<String> {
+        s.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn bare_ignore_ignores_everything() {
+        let directives = parse_ignore_directives(&lines("Title\n\n.. codetagger: ignore\n"));
+        assert_eq!(
+            directives,
+            [IgnoreDirective {
+                line: 2,
+                target: IgnoreTarget::Everything,
+            }]
+        );
+        assert!(ignores_reason(&directives, &Reason::NodejsTab));
+    }
+
+    #[test]
+    fn ignore_targets() {
+        let directives = parse_ignore_directives(&lines(
+            "   .. codetagger: ignore atlas-ui-tab, keyword:code example,, language:python",
+        ));
+        let targets: Vec<&IgnoreTarget> = directives.iter().map(|d| &d.target).collect();
+        assert_eq!(
+            targets,
+            [
+                &IgnoreTarget::Detector(String::from("atlas-ui-tab")),
+                &IgnoreTarget::Tag(Suppression::Keyword(String::from("code example"))),
+                &IgnoreTarget::Tag(Suppression::Language(Language::Python)),
+            ]
+        );
+        assert!(ignores_reason(&directives, &Reason::AtlasUiTab));
+        assert!(!ignores_reason(&directives, &Reason::NodejsTab));
+    }
+
+    #[test]
+    fn other_comments_arent_ignores() {
+        let directives = parse_ignore_directives(&lines(
+            ".. codetagger: check\n.. a comment\n:keywords: codetagger: ignore\n",
+        ));
+        assert!(directives.is_empty());
+    }
+}
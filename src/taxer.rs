@@ -1,52 +1,72 @@
-use std::collections::HashSet;
-
+use ansi_term::Colour::{Green, White};
 use clap::Parser;
 use itertools::Itertools;
-use walkdir::WalkDir;
 
-use codetagger::cli::Args;
+use codetagger::cli::{TaxerArgs, TaxerCommand};
+use codetagger::keywords::{clusters, keyword_uses, parse_renames, rename_keywords};
+use codetagger::repo::Repo;
 
-const MAX_DIST: usize = 3;
+// How many pages to list for each spelling, unless `--verbose`.
+const EXAMPLES: usize = 3;
 
 fn main() {
-    let args = Args::parse();
-    let _debug = false;
-    let _dryrun = args.dryrun;
-    let repo = args.repo;
-
-    let mut all_keywords: HashSet<String> = HashSet::default();
-
-    for entry in WalkDir::new(&repo) {
-        let entry = entry.unwrap();
-        let entry_path = entry.path();
-        if entry_path.is_dir() {
-            continue;
-        }
-        let filepath = String::from(entry_path.to_string_lossy());
+    let args = TaxerArgs::parse();
+    let repo = Repo::open(&args.repo).unwrap_or_else(|e| {
+        eprintln!("Unable to open {}: {e}", args.repo);
+        std::process::exit(1);
+    });
+    let mut cache = repo.cache(args.no_cache);
+    if let Err(e) = cache.scan(&repo.root) {
+        eprintln!("Unable to read {}: {e}", repo.root);
+        std::process::exit(1);
+    }
 
-        let keys = codetagger::meta::get_meta_keywords(&filepath);
-        if keys.is_some() {
-            for key in &keys.clone().unwrap() {
-                all_keywords.insert(key.to_string());
-            }
+    if let Some(TaxerCommand::Rename { pairs, file }) = &args.command {
+        let renames = parse_renames(pairs, file.as_deref());
+        println!("✏️ Renaming {} keyword(s)...", renames.len());
+        let count = rename_keywords(&cache, &renames, args.dryrun);
+        println!("{count} page(s) changed");
+        if args.dryrun {
+            println!(
+                "{}",
+                White
+                    .paint("\n👉 This was a dry run.\nTo update files, run with `--dryrun=false`.")
+            );
         }
+        cache.save();
+        return;
+    }
 
-        if keys.is_some() {
-            for pair in keys
-                .clone()
-                .unwrap()
-                .iter()
-                .cartesian_product(keys.unwrap().iter())
-            {
-                let dist = edit_distance::edit_distance(pair.0, pair.1);
-                if 1 <= dist && dist <= MAX_DIST {
-                    println!("found similar keywords {:?}", pair);
-                }
+    let uses = keyword_uses(&cache);
+    if args.verbose {
+        println!("{:#?}", uses.keys());
+    }
+
+    let clusters = clusters(&uses, args.max_dist);
+    println!(
+        "🔎 {} keywords, {} groups of similar keywords\n",
+        uses.len(),
+        clusters.len()
+    );
+
+    for cluster in &clusters {
+        let canonical = cluster.canonical();
+        println!("{}", White.bold().paint(canonical));
+        for (keyword, pages) in &cluster.variants {
+            let shown = if args.verbose { pages.len() } else { EXAMPLES };
+            let mut examples = pages.iter().take(shown).join(", ");
+            if pages.len() > shown {
+                examples += &format!(", ...and {} more", pages.len() - shown);
             }
+            let marker = if keyword == canonical {
+                Green.paint("✓").to_string()
+            } else {
+                String::from(" ")
+            };
+            println!("  {marker} {:>4}  {keyword:<24} {examples}", pages.len());
         }
+        println!();
     }
 
-    if args.verbose {
-        println!("{:#?}", all_keywords);
-    }
+    cache.save();
 }
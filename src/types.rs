@@ -2,48 +2,139 @@ use std::collections::BTreeSet;
 use std::fmt::Display;
 use std::str::FromStr;
 
+use serde::{Deserialize, Serialize};
+
 // The reason a file needs tagging.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Reason {
-    CodeExample(String),
+    /// What sort of example, and the string that showed it.
+    CodeExample(CodeExampleKind, String),
     Languages(BTreeSet<Language>),
     NodejsTab,
     CompassTab,
     AtlasApiTab,
     AtlasCliTab,
     AtlasUiTab,
-    Java(JavaSyncness),
+    /// Canonical ids of the language variants (like `java-sync`) on the page.
+    Variants(BTreeSet<String>),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum JavaSyncness {
-    Sync,
-    Async,
-    Both,
+/// Names of the detectors, as used in `.. codetagger: ignore <detector>`.
+pub const DETECTOR_IDS: &[&str] = &[
+    "code-example",
+    "languages",
+    "nodejs-tab",
+    "compass-tab",
+    "atlas-api-tab",
+    "atlas-cli-tab",
+    "atlas-ui-tab",
+    "variant-tabs",
+];
+
+impl Reason {
+    /// The name of the detector that found this Reason.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Reason::CodeExample(..) => "code-example",
+            Reason::Languages(_) => "languages",
+            Reason::NodejsTab => "nodejs-tab",
+            Reason::CompassTab => "compass-tab",
+            Reason::AtlasApiTab => "atlas-api-tab",
+            Reason::AtlasCliTab => "atlas-cli-tab",
+            Reason::AtlasUiTab => "atlas-ui-tab",
+            Reason::Variants(_) => "variant-tabs",
+        }
+    }
+
+    /// The meta keywords a page with this Reason should have.
+    pub fn keywords(&self) -> Vec<&'static str> {
+        match self {
+            Reason::CodeExample(kind, _) => vec!["code example", kind.keyword()],
+            Reason::NodejsTab => vec!["node.js"],
+            Reason::CompassTab => vec!["compass"],
+            Reason::AtlasApiTab => vec!["atlas api"],
+            Reason::AtlasCliTab => vec!["atlas cli"],
+            Reason::AtlasUiTab => vec!["atlas ui"],
+            Reason::Variants(ids) => ids
+                .iter()
+                .filter_map(|id| Variant::from_tabid(id))
+                .map(|v| v.taxonomy)
+                .collect(),
+            Reason::Languages(_) => vec![],
+        }
+    }
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::CodeExample(kind, s) => write!(f, "contains `{s}` ({kind})"),
+            Reason::Languages(langs) => {
+                let langs: Vec<String> = langs.iter().map(|l| l.to_string()).collect();
+                write!(f, "has code for {}", langs.join(", "))
+            }
+            Reason::NodejsTab => write!(f, "has a `nodejs` tab"),
+            Reason::CompassTab => write!(f, "has a `compass` tab"),
+            Reason::AtlasApiTab => write!(f, "has an Atlas API tab"),
+            Reason::AtlasCliTab => write!(f, "has an Atlas CLI tab"),
+            Reason::AtlasUiTab => write!(f, "has an Atlas UI tab"),
+            Reason::Variants(ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| format!("`{id}`")).collect();
+                match ids.len() {
+                    1 => write!(f, "has a {} tab", ids[0]),
+                    _ => write!(f, "has {} tabs", ids.join(" and ")),
+                }
+            }
+        }
+    }
 }
 
-// impl FromStr for JavaSyncness {
-//     type Err = ParseSyncnessError;
+/// What sort of code example a page has, from least to most involved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CodeExampleKind {
+    /// Code blocks and partial `literalinclude`s.
+    Snippet,
+    /// A `literalinclude` of a whole file.
+    Runnable,
+    /// Driver tabs.
+    Tabbed,
+}
+
+pub const CODE_EXAMPLE_KIND_KEYWORDS: &[&str] =
+    &["code snippet", "runnable example", "tabbed example"];
 
-//     fn from_str(s: &str) -> Result<Self, Self::Err> {
-//         match s {
-//             "java sync" => Ok(JavaSyncness::Sync),
-//             "java async" => Ok(JavaSyncness::Async),
-//             "java sync, java async" => Ok(JavaSyncness::Both),
-//             _ => Err(ParseSyncnessError),
-//         }
-//     }
-// }
+impl CodeExampleKind {
+    /// The keyword for pages whose most involved example is this kind.
+    pub fn keyword(&self) -> &'static str {
+        CODE_EXAMPLE_KIND_KEYWORDS[*self as usize]
+    }
+}
+
+impl Display for CodeExampleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            CodeExampleKind::Snippet => "snippet",
+            CodeExampleKind::Runnable => "runnable",
+            CodeExampleKind::Tabbed => "tabbed",
+        };
+        write!(f, "{s}")
+    }
+}
 
-#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+/// A programming language, as used in the `programming_language` facet.
+/// Sub-variants like `java-sync` or `motor` are described by `Variant`.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub enum Language {
     C,
     Cpp,
     Csharp,
+    Dart,
+    Elixir,
     Go,
     Java,
     Javascript,
     Kotlin,
+    Mojo,
     Perl,
     Php,
     Python,
@@ -54,59 +145,352 @@ pub enum Language {
     Swift,
 }
 
+/// What we know about a Language.
+#[derive(Debug)]
+pub struct LanguageInfo {
+    pub language: Language,
+    /// The value used in the `programming_language` facet.
+    pub taxonomy: &'static str,
+    /// A human-friendly name.
+    pub display: &'static str,
+    /// Other tabids and lexer names that mean this language.
+    pub aliases: &'static [&'static str],
+    /// File extensions, without the dot.
+    pub extensions: &'static [&'static str],
+}
+
+pub const LANGUAGES: &[LanguageInfo] = &[
+    LanguageInfo {
+        language: Language::C,
+        taxonomy: "c",
+        display: "C",
+        aliases: &[],
+        extensions: &["c", "h"],
+    },
+    LanguageInfo {
+        language: Language::Cpp,
+        taxonomy: "cpp",
+        display: "C++",
+        aliases: &["c++", "cxx"],
+        extensions: &["cpp", "cc", "cxx", "hpp"],
+    },
+    LanguageInfo {
+        language: Language::Csharp,
+        taxonomy: "csharp",
+        display: "C#",
+        aliases: &["c#", "cs"],
+        extensions: &["cs"],
+    },
+    LanguageInfo {
+        language: Language::Dart,
+        taxonomy: "dart",
+        display: "Dart",
+        aliases: &[],
+        extensions: &["dart"],
+    },
+    LanguageInfo {
+        language: Language::Elixir,
+        taxonomy: "elixir",
+        display: "Elixir",
+        aliases: &["ex"],
+        extensions: &["ex", "exs"],
+    },
+    LanguageInfo {
+        language: Language::Go,
+        taxonomy: "go",
+        display: "Go",
+        aliases: &["golang"],
+        extensions: &["go"],
+    },
+    LanguageInfo {
+        language: Language::Java,
+        taxonomy: "java",
+        display: "Java",
+        aliases: &[],
+        extensions: &["java"],
+    },
+    LanguageInfo {
+        language: Language::Javascript,
+        taxonomy: "javascript/typescript",
+        display: "JavaScript/TypeScript",
+        aliases: &["javascript", "js", "nodejs", "typescript", "ts"],
+        extensions: &["js", "mjs", "cjs", "ts", "mts", "cts"],
+    },
+    LanguageInfo {
+        language: Language::Kotlin,
+        taxonomy: "kotlin",
+        display: "Kotlin",
+        aliases: &["kt"],
+        extensions: &["kt", "kts"],
+    },
+    LanguageInfo {
+        language: Language::Mojo,
+        taxonomy: "mojo",
+        display: "Mojo",
+        aliases: &[],
+        extensions: &["mojo"],
+    },
+    LanguageInfo {
+        language: Language::Perl,
+        taxonomy: "perl",
+        display: "Perl",
+        aliases: &["pl"],
+        extensions: &["pl", "pm"],
+    },
+    LanguageInfo {
+        language: Language::Php,
+        taxonomy: "php",
+        display: "PHP",
+        aliases: &[],
+        extensions: &["php"],
+    },
+    LanguageInfo {
+        language: Language::Python,
+        taxonomy: "python",
+        display: "Python",
+        aliases: &["py", "python3"],
+        extensions: &["py"],
+    },
+    LanguageInfo {
+        language: Language::Ruby,
+        taxonomy: "ruby",
+        display: "Ruby",
+        aliases: &["rb"],
+        extensions: &["rb"],
+    },
+    LanguageInfo {
+        language: Language::Rust,
+        taxonomy: "rust",
+        display: "Rust",
+        aliases: &["rs"],
+        extensions: &["rs"],
+    },
+    LanguageInfo {
+        language: Language::Scala,
+        taxonomy: "scala",
+        display: "Scala",
+        aliases: &[],
+        extensions: &["scala"],
+    },
+    LanguageInfo {
+        language: Language::Shell,
+        taxonomy: "shell",
+        display: "Shell",
+        aliases: &["sh", "bash", "zsh"],
+        extensions: &["sh", "bash"],
+    },
+    LanguageInfo {
+        language: Language::Swift,
+        taxonomy: "swift",
+        display: "Swift",
+        aliases: &[],
+        extensions: &["swift"],
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Syncness {
+    Sync,
+    Async,
+}
+
+/// A flavor of a Language that the taxonomy tells apart,
+/// like the sync and async Java drivers.
+#[derive(Debug)]
+pub struct Variant {
+    /// The canonical name, which is also the usual tabid.
+    pub id: &'static str,
+    pub language: Language,
+    /// The taxonomy value for this variant.
+    pub taxonomy: &'static str,
+    /// A human-friendly name.
+    pub display: &'static str,
+    /// Other tabids that mean this variant.
+    pub aliases: &'static [&'static str],
+    pub syncness: Option<Syncness>,
+    pub framework: Option<&'static str>,
+}
+
+pub const VARIANTS: &[Variant] = &[
+    Variant {
+        id: "java-sync",
+        language: Language::Java,
+        taxonomy: "java sync",
+        display: "Java (Sync)",
+        aliases: &[],
+        syncness: Some(Syncness::Sync),
+        framework: None,
+    },
+    Variant {
+        id: "java-async",
+        language: Language::Java,
+        taxonomy: "java async",
+        display: "Java (Async)",
+        aliases: &["java-rs", "java-reactive-streams"],
+        syncness: Some(Syncness::Async),
+        framework: Some("Reactive Streams"),
+    },
+    Variant {
+        id: "kotlin-sync",
+        language: Language::Kotlin,
+        taxonomy: "kotlin sync",
+        display: "Kotlin (Sync)",
+        aliases: &[],
+        syncness: Some(Syncness::Sync),
+        framework: None,
+    },
+    Variant {
+        id: "kotlin-coroutine",
+        language: Language::Kotlin,
+        taxonomy: "kotlin coroutine",
+        display: "Kotlin (Coroutine)",
+        aliases: &[],
+        syncness: Some(Syncness::Async),
+        framework: Some("Coroutine"),
+    },
+    Variant {
+        id: "pymongo",
+        language: Language::Python,
+        taxonomy: "pymongo",
+        display: "Python (PyMongo)",
+        // Plain `python` tabs are PyMongo, the sync driver.
+        aliases: &["python"],
+        syncness: Some(Syncness::Sync),
+        framework: Some("PyMongo"),
+    },
+    Variant {
+        id: "pymongo-async",
+        language: Language::Python,
+        taxonomy: "pymongo async",
+        display: "Python (PyMongo Async)",
+        aliases: &[],
+        syncness: Some(Syncness::Async),
+        framework: Some("PyMongo"),
+    },
+    Variant {
+        id: "motor",
+        language: Language::Python,
+        taxonomy: "motor",
+        display: "Python (Motor)",
+        aliases: &["python-motor"],
+        syncness: Some(Syncness::Async),
+        framework: Some("Motor"),
+    },
+    Variant {
+        id: "rust-sync",
+        language: Language::Rust,
+        taxonomy: "rust sync",
+        display: "Rust (Sync)",
+        aliases: &[],
+        syncness: Some(Syncness::Sync),
+        framework: None,
+    },
+    Variant {
+        id: "rust-async",
+        language: Language::Rust,
+        taxonomy: "rust async",
+        display: "Rust (Async)",
+        aliases: &[],
+        syncness: Some(Syncness::Async),
+        framework: Some("Tokio"),
+    },
+    Variant {
+        id: "swift-sync",
+        language: Language::Swift,
+        taxonomy: "swift sync",
+        display: "Swift (Sync)",
+        aliases: &[],
+        syncness: Some(Syncness::Sync),
+        framework: None,
+    },
+    Variant {
+        id: "swift-async",
+        language: Language::Swift,
+        taxonomy: "swift async",
+        display: "Swift (Async)",
+        aliases: &[],
+        syncness: Some(Syncness::Async),
+        framework: None,
+    },
+];
+
+impl Variant {
+    /// The Variant for a tabid like `java-sync`, if it is one.
+    pub fn from_tabid(s: &str) -> Option<&'static Variant> {
+        VARIANTS
+            .iter()
+            .find(|v| v.id == s || v.aliases.contains(&s))
+    }
+}
+
+impl Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParseLangError;
 
 impl FromStr for Language {
     type Err = ParseLangError;
 
+    /// Accepts taxonomy values, aliases, and variant tabids.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "c" => Ok(Self::C),
-            "cpp" => Ok(Self::Cpp),
-            "csharp" => Ok(Self::Csharp),
-            "go" => Ok(Self::Go),
-            "java-async" => Ok(Self::Java),
-            "java-sync" => Ok(Self::Java),
-            "javascript/typescript" => Ok(Self::Javascript),
-            "kotlin-coroutine" => Ok(Self::Kotlin),
-            "kotlin" => Ok(Self::Kotlin),
-            "nodejs" => Ok(Self::Javascript),
-            "perl" => Ok(Self::Perl),
-            "php" => Ok(Self::Php),
-            "python" => Ok(Self::Python),
-            "ruby" => Ok(Self::Ruby),
-            "rust" => Ok(Self::Rust),
-            "rust-async" => Ok(Self::Rust),
-            "rust-sync" => Ok(Self::Rust),
-            "scala" => Ok(Self::Scala),
-            "shell" => Ok(Self::Shell),
-            "swift-sync" => Ok(Self::Swift),
-            "swift-async" => Ok(Self::Swift),
-            _ => Err(ParseLangError),
+        if let Some(info) = LANGUAGES
+            .iter()
+            .find(|l| l.taxonomy == s || l.aliases.contains(&s))
+        {
+            return Ok(info.language.clone());
+        }
+        match Variant::from_tabid(s) {
+            Some(variant) => Ok(variant.language.clone()),
+            None => Err(ParseLangError),
         }
     }
 }
 
+impl Language {
+    pub fn info(&self) -> &'static LanguageInfo {
+        LANGUAGES
+            .iter()
+            .find(|l| l.language == *self)
+            .expect("every Language is in LANGUAGES")
+    }
+
+    /// The value used in the `programming_language` facet.
+    pub fn taxonomy(&self) -> &'static str {
+        self.info().taxonomy
+    }
+
+    /// A human-friendly name.
+    pub fn display_name(&self) -> &'static str {
+        self.info().display
+    }
+
+    pub fn variants(&self) -> impl Iterator<Item = &'static Variant> + '_ {
+        VARIANTS.iter().filter(move |v| v.language == *self)
+    }
+
+    /// The language for a Pygments lexer name, as used by `code-block`
+    /// and `:language:`. Returns `None` for things that aren't programming
+    /// languages, like `json` or `text`.
+    pub fn from_lexer(s: &str) -> Option<Self> {
+        Self::from_str(&s.to_lowercase()).ok()
+    }
+
+    /// The language for a source file extension, without the dot.
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let ext = ext.to_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|l| l.extensions.contains(&ext.as_str()))
+            .map(|l| l.language.clone())
+    }
+}
+
 impl Display for Language {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match &self {
-            Self::C => "c",
-            Self::Cpp => "cpp",
-            Self::Csharp => "csharp",
-            Self::Go => "go",
-            Self::Java => "java",
-            Self::Javascript => "javascript/typescript",
-            Self::Kotlin => "kotlin",
-            Self::Perl => "perl",
-            Self::Php => "php",
-            Self::Python => "python",
-            Self::Ruby => "ruby",
-            Self::Rust => "rust",
-            Self::Scala => "scala",
-            Self::Shell => "shell",
-            Self::Swift => "swift",
-        };
-        write!(f, "{}", s)
+        write!(f, "{}", self.taxonomy())
     }
 }
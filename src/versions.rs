@@ -0,0 +1,169 @@
+//! Docs repos that keep each version in its own directory, each with its
+//! own `snooty.toml` (like `current/`, `upcoming/` and `v6.0/`).
+//!
+//! Only the current version gets tagged. That's the `current` directory
+//! unless `current-version` in `.codetagger/config.toml` (or `--docs-version`)
+//! says otherwise. Repos that keep versions on branches just have the
+//! checked-out branch, and are treated like any other repo.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::diagnostics::{diagnose, ExpectedTags};
+use crate::files::is_page;
+use crate::snooty::SNOOTY_TOML;
+use crate::suppressions::Suppressions;
+use crate::vfs::{is_file, Vfs};
+
+pub const DEFAULT_CURRENT_VERSION: &str = "current";
+
+/// The version directories in `repo`, by name.
+/// Empty if `repo` has its own `snooty.toml`, since then it isn't versioned.
+pub fn versions(vfs: &dyn Vfs, repo: &str) -> BTreeMap<String, String> {
+    let mut versions: BTreeMap<String, String> = BTreeMap::new();
+    let snooty_toml = |dir: &str| String::from(Path::new(dir).join(SNOOTY_TOML).to_string_lossy());
+    if is_file(vfs, &snooty_toml(repo)) {
+        return versions;
+    }
+    for path in vfs.list(repo).unwrap_or_default() {
+        if is_file(vfs, &snooty_toml(&path)) {
+            let name = Path::new(&path).file_name().unwrap_or_default();
+            versions.insert(name.to_string_lossy().to_string(), path);
+        }
+    }
+    versions
+}
+
+/// The directory to scan: the current version's if `repo` is versioned,
+/// otherwise `repo` itself. Fails if the current version isn't there.
+pub fn current_root(vfs: &dyn Vfs, repo: &str, current: Option<&str>) -> io::Result<String> {
+    let versions = versions(vfs, repo);
+    if versions.is_empty() {
+        return Ok(repo.to_string());
+    }
+    let current = current.unwrap_or(DEFAULT_CURRENT_VERSION);
+    versions.get(current).cloned().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "no `{current}` version in {repo} (found {}). Set `current-version` in .codetagger/config.toml.",
+                versions.keys().cloned().collect::<Vec<_>>().join(", ")
+            ),
+        )
+    })
+}
+
+/// What we found for one version.
+#[derive(Debug, Default)]
+pub struct VersionTags {
+    /// Expected tags for each page, by path relative to `source/`.
+    pub expected: BTreeMap<String, ExpectedTags>,
+    /// Pages (relative to `source/`) whose tags don't match what's expected.
+    pub needing_edits: BTreeSet<String>,
+}
+
+/// Scan the version at `root` without touching the cache on disk.
+pub fn version_tags(
+    vfs: Arc<dyn Vfs>,
+    root: &str,
+    config: &Config,
+    suppressions: &Suppressions,
+) -> io::Result<VersionTags> {
+    let mut cache = Cache::in_memory(config).with_vfs(vfs.clone());
+    let filepaths = cache.scan(root)?;
+    let strings = cache.code_example_strings();
+
+    let mut tags = VersionTags::default();
+    for path in filepaths.iter().filter(|p| is_page(p)) {
+        let mut expected = cache.expected_tags(path, &strings);
+        suppressions.filter(path, &mut expected);
+        let contents = vfs.read(path).unwrap_or_default();
+        let page = page_name(path);
+        if !diagnose(path, &contents, &expected).is_empty() {
+            tags.needing_edits.insert(page.clone());
+        }
+        tags.expected.insert(page, expected);
+    }
+    Ok(tags)
+}
+
+fn page_name(path: &str) -> String {
+    path.split("/source/").nth(1).unwrap_or(path).to_string()
+}
+
+/// Print how tagging in each version of `repo` compares with `current`.
+pub fn print_version_differences(
+    vfs: Arc<dyn Vfs>,
+    repo: &str,
+    config: &Config,
+    current: &str,
+    verbose: bool,
+) -> io::Result<()> {
+    let versions = versions(&*vfs, repo);
+    if versions.is_empty() {
+        println!("{repo} isn't versioned.");
+        return Ok(());
+    }
+    // Suppressions are kept at the top level for every version.
+    let suppressions = Suppressions::load(&*vfs, repo);
+    let tags: BTreeMap<&String, VersionTags> = versions
+        .iter()
+        .map(|(name, root)| {
+            let mut config = config.clone();
+            config.current_version = Some(name.clone());
+            config.load_project(&*vfs, repo);
+            Ok((
+                name,
+                version_tags(vfs.clone(), root, &config, &suppressions)?,
+            ))
+        })
+        .collect::<io::Result<_>>()?;
+    let Some(current_tags) = tags.get(&current.to_string()) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no `{current}` version in {repo}"),
+        ));
+    };
+
+    println!(
+        "\n{:<20} {:>6} {:>10} {:>12}",
+        "Version", "Pages", "Need edits", "Differ"
+    );
+    for (name, version) in &tags {
+        let differing: Vec<(&String, &ExpectedTags, &ExpectedTags)> = version
+            .expected
+            .iter()
+            .filter_map(|(page, expected)| {
+                let theirs = current_tags.expected.get(page)?;
+                (expected != theirs).then_some((page, expected, theirs))
+            })
+            .collect();
+        let marker = if name.as_str() == current { " *" } else { "" };
+        println!(
+            "{:<20} {:>6} {:>10} {:>12}",
+            format!("{name}{marker}"),
+            version.expected.len(),
+            version.needing_edits.len(),
+            differing.len()
+        );
+        if verbose {
+            for (page, expected, theirs) in differing {
+                println!("    {page}");
+                println!(
+                    "      {name}: {:?} {:?}",
+                    expected.keywords, expected.languages
+                );
+                println!(
+                    "      {current}: {:?} {:?}",
+                    theirs.keywords, theirs.languages
+                );
+            }
+        }
+    }
+    println!("\n* is the current version. \"Differ\" counts pages in both versions that need different tags.");
+    Ok(())
+}
@@ -0,0 +1,415 @@
+//! Where file contents come from.
+//!
+//! Everything that reads or edits docs files goes through a `Vfs`, so the
+//! same code can run on the working tree (`DiskFs`), on contents held in
+//! memory (`MemoryFs`), or on a commit in a local git repo (`GitFs`).
+//! Paths are the same strings either way, like `<repo>/source/index.txt`.
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use git2::{ObjectType, Oid, Repository};
+
+/// What we need to know about a file or directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub is_dir: bool,
+    /// Size in bytes (0 for directories).
+    pub len: u64,
+    /// Whether it's a symbolic link. The other fields describe what it points to.
+    pub is_symlink: bool,
+}
+
+pub trait Vfs: Debug + Send + Sync {
+    fn read(&self, path: &str) -> io::Result<String>;
+    fn read_bytes(&self, path: &str) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &str, contents: &str) -> io::Result<()>;
+    /// The paths of the files and directories directly inside `dir`, sorted.
+    fn list(&self, dir: &str) -> io::Result<Vec<String>>;
+    fn metadata(&self, path: &str) -> io::Result<Metadata>;
+}
+
+/// Whether `path` is a file (and not a directory) in `vfs`.
+pub fn is_file(vfs: &dyn Vfs, path: &str) -> bool {
+    vfs.metadata(path).is_ok_and(|m| !m.is_dir)
+}
+
+/// Every file under `dir`, sorted, leaving out anything named in `skip`.
+/// Like `WalkDir`, links to directories aren't followed.
+/// Fails if `dir` itself can't be listed; unreadable subdirectories are skipped.
+pub fn walk(vfs: &dyn Vfs, dir: &str, skip: &[&str]) -> io::Result<Vec<String>> {
+    let mut files: Vec<String> = vec![];
+    for path in vfs.list(dir)? {
+        let name = Path::new(&path).file_name().unwrap_or_default();
+        if skip.iter().any(|s| name == *s) {
+            continue;
+        }
+        match vfs.metadata(&path) {
+            Ok(metadata) if metadata.is_dir && metadata.is_symlink => {}
+            Ok(metadata) if metadata.is_dir => {
+                files.extend(walk(vfs, &path, skip).unwrap_or_default())
+            }
+            Ok(_) => files.push(path),
+            Err(_) => {}
+        }
+    }
+    Ok(files)
+}
+
+fn join(dir: &str, name: &str) -> String {
+    String::from(Path::new(dir).join(name).to_string_lossy())
+}
+
+/// The real filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskFs;
+
+impl Vfs for DiskFs {
+    fn read(&self, path: &str) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn read_bytes(&self, path: &str) -> io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+
+    fn write(&self, path: &str, contents: &str) -> io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, contents)
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<String>> {
+        let mut paths: Vec<String> = std::fs::read_dir(dir)?
+            .flatten()
+            .map(|entry| String::from(entry.path().to_string_lossy()))
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        let is_symlink = std::fs::symlink_metadata(path)?.is_symlink();
+        let metadata = std::fs::metadata(path)?;
+        Ok(Metadata {
+            is_dir: metadata.is_dir(),
+            len: if metadata.is_dir() { 0 } else { metadata.len() },
+            is_symlink,
+        })
+    }
+}
+
+/// Files held in memory, by path. Directories are implied by the paths.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    files: Mutex<BTreeMap<String, String>>,
+}
+
+impl MemoryFs {
+    pub fn new(files: impl IntoIterator<Item = (String, String)>) -> MemoryFs {
+        MemoryFs {
+            files: Mutex::new(files.into_iter().collect()),
+        }
+    }
+
+    /// Everything in it, including anything written since it was made.
+    pub fn files(&self) -> BTreeMap<String, String> {
+        self.files.lock().unwrap().clone()
+    }
+}
+
+fn not_found(path: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("{path} not found"))
+}
+
+impl Vfs for MemoryFs {
+    fn read(&self, path: &str) -> io::Result<String> {
+        let files = self.files.lock().unwrap();
+        files.get(path).cloned().ok_or_else(|| not_found(path))
+    }
+
+    fn read_bytes(&self, path: &str) -> io::Result<Vec<u8>> {
+        self.read(path).map(String::into_bytes)
+    }
+
+    fn write(&self, path: &str, contents: &str) -> io::Result<()> {
+        let mut files = self.files.lock().unwrap();
+        files.insert(path.to_string(), contents.to_string());
+        Ok(())
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<String>> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let files = self.files.lock().unwrap();
+        let mut paths: Vec<String> = files
+            .keys()
+            .filter_map(|path| path.strip_prefix(&prefix))
+            .filter_map(|rest| rest.split('/').next())
+            .map(|name| join(dir, name))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        if paths.is_empty() {
+            return Err(not_found(dir));
+        }
+        Ok(paths)
+    }
+
+    fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        let files = self.files.lock().unwrap();
+        if let Some(contents) = files.get(path) {
+            return Ok(Metadata {
+                is_dir: false,
+                len: contents.len() as u64,
+                is_symlink: false,
+            });
+        }
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        if files.keys().any(|p| p.starts_with(&prefix)) {
+            return Ok(Metadata {
+                is_dir: true,
+                len: 0,
+                is_symlink: false,
+            });
+        }
+        Err(not_found(path))
+    }
+}
+
+/// The tree of a commit in a local git repo. Read-only.
+pub struct GitFs {
+    /// The path the files appear under, as given to `open`.
+    root: String,
+    /// Where `root` is inside the git repo.
+    prefix: PathBuf,
+    repo: Mutex<Repository>,
+    tree: Oid,
+}
+
+impl GitFs {
+    /// Read the files of `rev` (anything `git rev-parse` understands, like a
+    /// tag, branch or commit) from the git repo containing `root`.
+    /// Fails if `root` isn't in a (non-bare) git repo or `rev` isn't found.
+    pub fn open(root: &str, rev: &str) -> Result<GitFs, git2::Error> {
+        let repo = Repository::discover(root)?;
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())?
+            .id();
+        let workdir = repo
+            .workdir()
+            .and_then(|dir| dir.canonicalize().ok())
+            .ok_or_else(|| git2::Error::from_str("can't read a revision from a bare repo"))?;
+        let prefix = Path::new(root)
+            .canonicalize()
+            .ok()
+            .and_then(|root| root.strip_prefix(&workdir).ok().map(Path::to_path_buf))
+            .unwrap_or_default();
+        Ok(GitFs {
+            root: root.to_string(),
+            prefix,
+            repo: Mutex::new(repo),
+            tree,
+        })
+    }
+
+    // Where `path` is in the tree.
+    fn tree_path(&self, path: &str) -> io::Result<PathBuf> {
+        let rel = Path::new(path)
+            .strip_prefix(&self.root)
+            .map_err(|_| not_found(path))?;
+        Ok(self.prefix.join(rel))
+    }
+}
+
+impl Debug for GitFs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitFs")
+            .field("root", &self.root)
+            .field("prefix", &self.prefix)
+            .field("tree", &self.tree)
+            .finish()
+    }
+}
+
+fn git_error(e: git2::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, e.message().to_string())
+}
+
+impl Vfs for GitFs {
+    fn read(&self, path: &str) -> io::Result<String> {
+        let bytes = self.read_bytes(path)?;
+        Ok(String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    fn read_bytes(&self, path: &str) -> io::Result<Vec<u8>> {
+        let tree_path = self.tree_path(path)?;
+        let repo = self.repo.lock().unwrap();
+        let tree = repo.find_tree(self.tree).map_err(git_error)?;
+        let entry = tree.get_path(&tree_path).map_err(git_error)?;
+        let object = entry.to_object(&repo).map_err(git_error)?;
+        let blob = object.as_blob().ok_or_else(|| not_found(path))?;
+        Ok(blob.content().to_vec())
+    }
+
+    fn write(&self, path: &str, _contents: &str) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("Can't write {path}: git revisions are read-only"),
+        ))
+    }
+
+    fn list(&self, dir: &str) -> io::Result<Vec<String>> {
+        let tree_path = self.tree_path(dir)?;
+        let repo = self.repo.lock().unwrap();
+        let root = repo.find_tree(self.tree).map_err(git_error)?;
+        let tree = if tree_path.as_os_str().is_empty() {
+            root
+        } else {
+            let entry = root.get_path(&tree_path).map_err(git_error)?;
+            let object = entry.to_object(&repo).map_err(git_error)?;
+            object.peel_to_tree().map_err(git_error)?
+        };
+        let mut paths: Vec<String> = tree
+            .iter()
+            .filter_map(|entry| entry.name().ok().map(|name| join(dir, name)))
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn metadata(&self, path: &str) -> io::Result<Metadata> {
+        let tree_path = self.tree_path(path)?;
+        if tree_path.as_os_str().is_empty() {
+            return Ok(Metadata {
+                is_dir: true,
+                len: 0,
+                is_symlink: false,
+            });
+        }
+        let repo = self.repo.lock().unwrap();
+        let tree = repo.find_tree(self.tree).map_err(git_error)?;
+        let entry = tree.get_path(&tree_path).map_err(git_error)?;
+        if entry.kind() == Some(ObjectType::Tree) {
+            return Ok(Metadata {
+                is_dir: true,
+                len: 0,
+                is_symlink: false,
+            });
+        }
+        let object = entry.to_object(&repo).map_err(git_error)?;
+        let len = object.as_blob().map_or(0, |blob| blob.size() as u64);
+        Ok(Metadata {
+            is_dir: false,
+            len,
+            // Git doesn't resolve links, so these are read as the link's target path.
+            is_symlink: entry.filemode() == 0o120000,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("codetagger-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        String::from(dir.to_string_lossy())
+    }
+
+    fn memory_fs() -> MemoryFs {
+        MemoryFs::new(
+            [
+                ("/docs/source/index.txt", "index"),
+                ("/docs/source/includes/a.rst", "a"),
+                ("/docs/.git/HEAD", "ref"),
+            ]
+            .map(|(path, contents)| (path.to_string(), contents.to_string())),
+        )
+    }
+
+    #[test]
+    fn memory_fs_lists_and_walks() {
+        let vfs = memory_fs();
+        assert_eq!(
+            vfs.list("/docs/source").unwrap(),
+            ["/docs/source/includes", "/docs/source/index.txt"]
+        );
+        assert!(vfs.metadata("/docs/source/includes").unwrap().is_dir);
+        assert_eq!(vfs.metadata("/docs/source/index.txt").unwrap().len, 5);
+        assert!(vfs.metadata("/docs/nope").is_err());
+        assert_eq!(
+            walk(&vfs, "/docs", &[".git"]).unwrap(),
+            ["/docs/source/includes/a.rst", "/docs/source/index.txt"]
+        );
+    }
+
+    #[test]
+    fn walking_a_missing_dir_fails() {
+        assert!(walk(&memory_fs(), "/nope", &[]).is_err());
+        assert!(walk(&DiskFs, "/nonexistent/codetagger/repo", &[]).is_err());
+    }
+
+    #[test]
+    fn memory_fs_writes() {
+        let vfs = memory_fs();
+        vfs.write("/docs/source/new.txt", "new").unwrap();
+        assert_eq!(vfs.read("/docs/source/new.txt").unwrap(), "new");
+        assert_eq!(vfs.files().len(), 4);
+    }
+
+    #[test]
+    fn walk_doesnt_follow_links_to_dirs() {
+        let dir = temp_dir("walk");
+        std::fs::create_dir(format!("{dir}/source")).unwrap();
+        std::fs::write(format!("{dir}/source/index.txt"), "index").unwrap();
+        std::os::unix::fs::symlink(&dir, format!("{dir}/source/loop")).unwrap();
+        std::os::unix::fs::symlink("index.txt", format!("{dir}/source/link.txt")).unwrap();
+
+        assert_eq!(
+            walk(&DiskFs, &dir, &[]).unwrap(),
+            [
+                format!("{dir}/source/index.txt"),
+                format!("{dir}/source/link.txt")
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn git_fs_reads_a_commit() {
+        let dir = temp_dir("git");
+        let repo = Repository::init(&dir).unwrap();
+        std::fs::create_dir(format!("{dir}/source")).unwrap();
+        std::fs::write(format!("{dir}/source/index.txt"), "committed").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("source/index.txt")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        std::fs::write(format!("{dir}/source/index.txt"), "changed").unwrap();
+
+        assert!(GitFs::open(&dir, "no-such-rev").is_err());
+        let vfs = GitFs::open(&dir, "HEAD").unwrap();
+        let page = format!("{dir}/source/index.txt");
+        assert_eq!(vfs.read(&page).unwrap(), "committed");
+        assert_eq!(walk(&vfs, &dir, &[]).unwrap(), vec![page.clone()]);
+        assert!(vfs.metadata(&format!("{dir}/source")).unwrap().is_dir);
+        assert!(vfs.write(&page, "no").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn git_fs_needs_a_git_repo() {
+        let dir = temp_dir("not-git");
+        assert!(GitFs::open(&dir, "HEAD").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+//! Checking keywords against the approved taxonomy.
+//!
+//! The approved keywords live in `.codetagger/keywords` in the docs repo,
+//! one per line. A line can also list other spellings that should be
+//! replaced with it, after a tab:
+//!
+//! ```text
+//! node.js<TAB>nodejs, node
+//! atlas cli
+//! ```
+//!
+//! Keywords that only differ from an approved one by case are fixed too.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use ansi_term::Colour::{Red, Yellow};
+
+use crate::cache::{Cache, CACHE_DIR};
+use crate::files::set_meta_keywords;
+use crate::vfs::Vfs;
+
+const VOCABULARY_FILE: &str = "keywords";
+
+#[derive(Debug, Default)]
+pub struct Vocabulary {
+    pub approved: BTreeSet<String>,
+    /// Other spellings, and the approved keyword they should become.
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Vocabulary {
+    /// Load the approved keywords from `path`, or from
+    /// `.codetagger/keywords` in `repo` if there isn't one.
+    pub fn load(vfs: &dyn Vfs, repo: &str, path: Option<&str>) -> Vocabulary {
+        // A list given on the command line is always read from disk.
+        let (path, contents) = match path {
+            Some(path) => (PathBuf::from(path), read_to_string(path)),
+            None => {
+                let path = vocabulary_path(repo);
+                let contents = vfs.read(&path.to_string_lossy());
+                (path, contents)
+            }
+        };
+        let contents =
+            contents.unwrap_or_else(|_| panic!("Unable to read keyword list {}", path.display()));
+
+        let mut vocabulary = Vocabulary::default();
+        for line in contents.lines() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (keyword, aliases) = line.split_once('\t').unwrap_or((line, ""));
+            let keyword = keyword.trim().to_string();
+            for alias in aliases
+                .split(',')
+                .map(|a| a.trim())
+                .filter(|a| !a.is_empty())
+            {
+                vocabulary
+                    .aliases
+                    .insert(alias.to_string(), keyword.clone());
+            }
+            vocabulary.approved.insert(keyword);
+        }
+        vocabulary
+    }
+
+    /// What `keyword` should be instead, if it isn't approved.
+    pub fn check(&self, keyword: &str) -> Option<Problem> {
+        if self.approved.contains(keyword) {
+            return None;
+        }
+        if let Some(canonical) = self.aliases.get(keyword) {
+            return Some(Problem::Alias(keyword.to_string(), canonical.clone()));
+        }
+        match self
+            .approved
+            .iter()
+            .find(|k| k.to_lowercase() == keyword.to_lowercase())
+        {
+            Some(canonical) => Some(Problem::Alias(keyword.to_string(), canonical.clone())),
+            None => Some(Problem::Unknown(keyword.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Problem {
+    /// A known other spelling, and what it should be.
+    Alias(String, String),
+    /// A keyword that isn't in the list at all.
+    Unknown(String),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Alias(from, to) => write!(f, "{} should be `{to}`", Yellow.paint(from)),
+            Problem::Unknown(k) => write!(f, "{} isn't an approved keyword", Red.paint(k)),
+        }
+    }
+}
+
+/// Check every page's keywords against `vocabulary`, replacing
+/// known aliases with the approved keyword.
+/// Returns how many problems were found.
+pub fn check_vocabulary(cache: &Cache, vocabulary: &Vocabulary, dryrun: bool) -> usize {
+    let mut count = 0;
+    for (path, entry) in cache.entries() {
+        let Some(keywords) = &entry.keywords else {
+            continue;
+        };
+        let problems: Vec<Problem> = keywords
+            .iter()
+            .filter_map(|k| vocabulary.check(k))
+            .collect();
+        if problems.is_empty() {
+            continue;
+        }
+        count += problems.len();
+        for problem in &problems {
+            println!("{path}: {problem}");
+        }
+
+        // We can't edit includes, so just report them.
+        if path.contains("/includes/") || !problems.iter().any(|p| matches!(p, Problem::Alias(..)))
+        {
+            continue;
+        }
+        let fixed: Vec<String> = keywords
+            .iter()
+            .map(|k| match vocabulary.check(k) {
+                Some(Problem::Alias(_, canonical)) => canonical,
+                _ => k.clone(),
+            })
+            .collect();
+        set_meta_keywords(cache.vfs(), path, &fixed, dryrun);
+    }
+    count
+}
+
+fn vocabulary_path(repo: &str) -> PathBuf {
+    Path::new(repo).join(CACHE_DIR).join(VOCABULARY_FILE)
+}
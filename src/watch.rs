@@ -0,0 +1,106 @@
+//! Re-checking pages as they're edited.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use ansi_term::Colour::Green;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::cache::Cache;
+use crate::diagnostics::diagnose;
+use crate::files::is_page;
+use crate::repo::Repo;
+use crate::vfs::is_file;
+
+// How long to wait for more events before re-checking,
+// since editors often write a file several times when saving.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watch `repo`'s `source/` dir, printing diagnostics for every changed page
+/// and every page that (transitively) includes a changed file.
+/// `cache` should already have been filled in with `Cache::scan`.
+pub fn watch(repo: &Repo, cache: &mut Cache) {
+    let root = Path::new(&repo.root)
+        .canonicalize()
+        .expect("Oops. Problem opening repo.");
+    let source = root.join("source");
+
+    let (tx, rx) = channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx).expect("Unable to start file watcher");
+    watcher
+        .watch(&source, RecursiveMode::Recursive)
+        .expect("Unable to watch source directory");
+
+    println!("👀 Watching {} for changes...", source.display());
+
+    while let Ok(event) = rx.recv() {
+        let mut changed: BTreeSet<String> = BTreeSet::default();
+        add_changed_paths(event, &repo.root, &root, &mut changed);
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            add_changed_paths(event, &repo.root, &root, &mut changed);
+        }
+
+        for path in &changed {
+            if is_file(cache.vfs(), path) {
+                cache.get(path);
+            } else {
+                cache.remove(path);
+            }
+        }
+
+        let mut affected: BTreeSet<String> = BTreeSet::default();
+        for path in &changed {
+            affected.insert(path.clone());
+            affected.extend(cache.transitive_includers(path));
+        }
+
+        let strings = cache.code_example_strings();
+        let suppressions = repo.suppressions();
+        for page in affected {
+            if !is_page(&page) || !is_file(cache.vfs(), &page) {
+                continue;
+            }
+            let mut expected = cache.expected_tags(&page, &strings);
+            suppressions.filter(&page, &mut expected);
+            let contents = cache.vfs().read(&page).unwrap_or_default();
+            let diagnostics = diagnose(&page, &contents, &expected);
+            if diagnostics.is_empty() {
+                println!("{} {page}", Green.paint("✓"));
+            }
+            for diagnostic in diagnostics {
+                println!("{diagnostic}");
+            }
+        }
+
+        cache.save();
+    }
+}
+
+// Paths in events are absolute, but the cache uses paths
+// relative to however `repo` was spelled on the command line.
+fn add_changed_paths(
+    event: notify::Result<Event>,
+    repo: &str,
+    root: &Path,
+    changed: &mut BTreeSet<String>,
+) {
+    let Ok(event) = event else {
+        return;
+    };
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) {
+        return;
+    }
+    for path in event.paths {
+        if path.is_dir() {
+            continue;
+        }
+        if let Ok(rel) = path.strip_prefix(root) {
+            changed.insert(String::from(Path::new(repo).join(rel).to_string_lossy()));
+        }
+    }
+}
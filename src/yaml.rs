@@ -0,0 +1,202 @@
+//! Giza-style YAML files (`steps-*.yaml`, `extracts-*.yaml` and
+//! `options-*.yaml`) that are rendered into includes at build time.
+//!
+//! Pages never include the YAML file itself, but a generated name like
+//! `/includes/steps/foo.rst`, so we work out those names and turn the
+//! YAML into the RST it would render as for the detectors to look at.
+
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_yaml::Value;
+
+const KINDS: &[&str] = &["steps", "extracts", "options"];
+
+// Fields whose values are RST.
+const RST_FIELDS: &[&str] = &["pre", "content", "post", "description", "action"];
+
+/// Whether `path` is a YAML file that renders into includes.
+pub fn renders_to_rst(path: &str) -> bool {
+    kind(path).is_some()
+}
+
+// `steps`, `extracts` or `options`, from a name like `steps-foo.yaml`.
+fn kind(path: &str) -> Option<&'static str> {
+    if !path.contains("/source/") {
+        return None;
+    }
+    let name = Path::new(path).file_name()?.to_str()?;
+    let stem = name.strip_suffix(".yaml")?;
+    KINDS
+        .iter()
+        .find(|kind| stem.starts_with(&format!("{kind}-")))
+        .copied()
+}
+
+fn documents(contents: &str) -> Vec<Value> {
+    let mut documents: Vec<Value> = vec![];
+    for document in serde_yaml::Deserializer::from_str(contents) {
+        match Value::deserialize(document) {
+            Ok(Value::Null) => {}
+            Ok(value) => documents.push(value),
+            Err(e) => {
+                eprintln!("Unable to parse YAML: {e}");
+                break;
+            }
+        }
+    }
+    documents
+}
+
+/// The RST `contents` would render as, near enough for the detectors.
+pub fn to_rst(contents: &str) -> String {
+    let mut rst = String::new();
+    for document in documents(contents) {
+        render(&document, &mut rst);
+    }
+    rst
+}
+
+fn render(value: &Value, rst: &mut String) {
+    match value {
+        Value::String(s) => {
+            rst.push_str(s);
+            rst.push_str("\n\n");
+        }
+        Value::Sequence(items) => items.iter().for_each(|item| render(item, rst)),
+        Value::Mapping(map) => {
+            if let Some(code) = map.get("code").and_then(|c| c.as_str()) {
+                let language = map.get("language").and_then(|l| l.as_str()).unwrap_or("");
+                rst.push_str(&format!(".. code-block:: {language}\n\n"));
+                for line in code.lines() {
+                    rst.push_str(&format!("   {line}\n"));
+                }
+                rst.push('\n');
+            }
+            for field in RST_FIELDS {
+                if let Some(value) = map.get(*field) {
+                    render(value, rst);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The include paths (relative to `source/`) that pages use for the
+/// YAML file at `path`.
+pub fn generated_names(path: &str, contents: &str) -> Vec<String> {
+    let Some(kind) = kind(path) else {
+        return vec![];
+    };
+    let rel = path.split("/source/").nth(1).unwrap_or_default();
+    let rel = Path::new(rel);
+    let dir = rel
+        .parent()
+        .map(|d| d.to_string_lossy())
+        .unwrap_or_default();
+    let stem = rel
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+
+    let mut names = vec![format!("{dir}/{stem}.rst")];
+    let get = |document: &Value, field: &str| document.get(field)?.as_str().map(String::from);
+    match kind {
+        "steps" => {
+            let name = stem.trim_start_matches("steps-");
+            names.push(format!("{dir}/steps/{name}.rst"));
+        }
+        "extracts" => {
+            for document in documents(contents) {
+                if let Some(r) = get(&document, "ref") {
+                    names.push(format!("{dir}/extracts/{r}.rst"));
+                }
+            }
+        }
+        "options" => {
+            for document in documents(contents) {
+                if let (Some(program), Some(name)) =
+                    (get(&document, "program"), get(&document, "name"))
+                {
+                    names.push(format!("{dir}/option/option-{program}-{name}.rst"));
+                }
+            }
+        }
+        _ => {}
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_giza_yaml_renders_to_rst() {
+        assert!(renders_to_rst("/docs/source/includes/steps-install.yaml"));
+        assert!(renders_to_rst("/docs/source/includes/extracts-common.yaml"));
+        assert!(!renders_to_rst("/docs/source/includes/toc-install.yaml"));
+        assert!(!renders_to_rst("/docs/config/steps-install.yaml"));
+    }
+
+    #[test]
+    fn steps_names() {
+        assert_eq!(
+            generated_names("/docs/source/includes/steps-install.yaml", ""),
+            ["includes/steps-install.rst", "includes/steps/install.rst"]
+        );
+    }
+
+    #[test]
+    fn extracts_and_options_names() {
+        let extracts = "ref: first\ncontent: One\n---\nref: second\ncontent: Two\n";
+        assert_eq!(
+            generated_names("/docs/source/includes/extracts-common.yaml", extracts),
+            [
+                "includes/extracts-common.rst",
+                "includes/extracts/first.rst",
+                "includes/extracts/second.rst",
+            ]
+        );
+        let options = "program: mongod\nname: port\ndescription: The port.\n";
+        assert_eq!(
+            generated_names("/docs/source/includes/options-mongod.yaml", options),
+            [
+                "includes/options-mongod.rst",
+                "includes/option/option-mongod-port.rst",
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_code_and_rst_fields() {
+        let contents = "\
+title: Install
+pre: Run the installer.
+action:
+  language: python
+  code: |
+    import pymongo
+    print(1)
+---
+content: |
+  .. literalinclude:: /code/example.go
+";
+        assert_eq!(
+            to_rst(contents),
+            "\
+Run the installer.
+
+.. code-block:: python
+
+   import pymongo
+   print(1)
+
+.. literalinclude:: /code/example.go
+
+
+"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Drive `codetagger-lsp` over stdio like an editor would.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+const PAGE: &str = "\
+=====
+Index
+=====
+
+.. tabs-drivers::
+
+   .. tab::
+      :tabid: python
+
+      .. code-block:: python
+
+         print(1)
+";
+
+// A docs repo with its project in `version` (or at the top, if empty).
+fn docs_repo(name: &str, version: &str) -> PathBuf {
+    let repo = std::env::temp_dir().join(format!("codetagger-{name}-{}", std::process::id()));
+    let root = repo.join(version);
+    std::fs::create_dir_all(root.join("source")).unwrap();
+    std::fs::write(root.join("snooty.toml"), "name = \"test\"\n").unwrap();
+    std::fs::write(root.join("source/index.txt"), PAGE).unwrap();
+    repo
+}
+
+fn spawn(repo: &Path) -> (Child, ChildStdin, BufReader<ChildStdout>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_codetagger-lsp"))
+        .env("XDG_CACHE_HOME", repo.join(".cache"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let stdin = child.stdin.take().unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    (child, stdin, stdout)
+}
+
+fn initialize(stdin: &mut ChildStdin, stdout: &mut BufReader<ChildStdout>, repo: &Path) {
+    send(
+        stdin,
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "capabilities": {},
+                "rootUri": format!("file://{}", repo.display()),
+            },
+        }),
+    );
+    let response = receive(stdout);
+    assert_eq!(response["id"], 1);
+    send(
+        stdin,
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+    );
+}
+
+fn shut_down(stdin: &mut ChildStdin, stdout: &mut BufReader<ChildStdout>) {
+    send(
+        stdin,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "shutdown"}),
+    );
+    let response = receive(stdout);
+    assert_eq!(response["id"], 2);
+    send(stdin, json!({"jsonrpc": "2.0", "method": "exit"}));
+}
+
+fn send(stdin: &mut ChildStdin, message: Value) {
+    let body = message.to_string();
+    write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    stdin.flush().unwrap();
+}
+
+fn receive(stdout: &mut BufReader<ChildStdout>) -> Value {
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        stdout.read_line(&mut header).unwrap();
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(n) = header.strip_prefix("Content-Length: ") {
+            length = n.parse().unwrap();
+        }
+    }
+    let mut body = vec![0; length];
+    stdout.read_exact(&mut body).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn wait(child: &mut Child) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if let Some(status) = child.try_wait().unwrap() {
+            return status.success();
+        }
+        sleep(Duration::from_millis(50));
+    }
+    child.kill().unwrap();
+    false
+}
+
+#[test]
+fn publishes_diagnostics_and_exits() {
+    let repo = docs_repo("lsp", "");
+    let page = repo.join("source/index.txt");
+    let uri = format!("file://{}", page.display());
+
+    let (mut child, mut stdin, mut stdout) = spawn(&repo);
+    initialize(&mut stdin, &mut stdout, &repo);
+
+    send(
+        &mut stdin,
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": {
+                "textDocument": {
+                    "uri": uri,
+                    "languageId": "restructuredtext",
+                    "version": 1,
+                    "text": PAGE,
+                },
+            },
+        }),
+    );
+    let published = receive(&mut stdout);
+    assert_eq!(published["method"], "textDocument/publishDiagnostics");
+    assert_eq!(published["params"]["uri"], uri);
+    let messages: Vec<&str> = published["params"]["diagnostics"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|d| d["message"].as_str().unwrap())
+        .collect();
+    assert!(
+        messages.iter().any(|m| m.contains("python")),
+        "{messages:?}"
+    );
+
+    shut_down(&mut stdin, &mut stdout);
+
+    let exited = wait(&mut child);
+    std::fs::remove_dir_all(&repo).unwrap();
+    assert!(exited, "codetagger-lsp didn't exit after `exit`");
+}
+
+#[test]
+fn reports_a_missing_current_version_and_stays_up() {
+    let repo = docs_repo("lsp-versions", "v6.0");
+
+    let (mut child, mut stdin, mut stdout) = spawn(&repo);
+    initialize(&mut stdin, &mut stdout, &repo);
+    let shown = receive(&mut stdout);
+    assert_eq!(shown["method"], "window/showMessage");
+    let message = shown["params"]["message"].as_str().unwrap();
+    assert!(message.contains("current"), "{message}");
+
+    shut_down(&mut stdin, &mut stdout);
+
+    let exited = wait(&mut child);
+    std::fs::remove_dir_all(&repo).unwrap();
+    assert!(exited, "codetagger-lsp didn't exit after `exit`");
+}